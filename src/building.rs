@@ -0,0 +1,118 @@
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+
+use crate::elevator::CallOrdering;
+use crate::power::RescueOrder;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildingConfig {
+    pub floors: i32,
+    pub lowest_floor: i32,
+    pub cars: usize,
+    pub capacity: i32,
+    pub orderings: Vec<CallOrdering>,
+    pub recall_floor: Option<i32>,
+    pub battery_cars: usize,
+    pub battery_seconds: f64,
+    pub rescue_order: RescueOrder,
+    pub danger_order: Vec<i32>,
+}
+
+impl Default for BuildingConfig {
+    fn default() -> Self {
+        BuildingConfig {
+            floors: 4,
+            lowest_floor: 0,
+            cars: 3,
+            capacity: 2,
+            orderings: vec![CallOrdering::Nearest],
+            recall_floor: None,
+            battery_cars: 1,
+            battery_seconds: 300.0,
+            rescue_order: RescueOrder::Nearest,
+            danger_order: Vec::new(),
+        }
+    }
+}
+
+impl BuildingConfig {
+    pub fn top_floor(&self) -> i32 {
+        self.lowest_floor + self.floors - 1
+    }
+
+    pub fn floor_range(&self) -> RangeInclusive<i32> {
+        self.lowest_floor..=self.top_floor()
+    }
+
+    pub fn contains(&self, floor: i32) -> bool {
+        self.floor_range().contains(&floor)
+    }
+
+    pub fn lobby_floor(&self) -> i32 {
+        if self.contains(0) { 0 } else { self.lowest_floor }
+    }
+
+    pub fn recall_floor(&self) -> i32 {
+        self.recall_floor.unwrap_or_else(|| self.lobby_floor())
+    }
+
+    pub fn danger_rank(&self, floor: i32) -> usize {
+        match self.danger_order.iter().position(|&f| f == floor) {
+            Some(rank) => rank,
+            None => self.danger_order.len() + (self.top_floor() - floor) as usize,
+        }
+    }
+
+    pub fn ordering_for(&self, car: usize) -> CallOrdering {
+        match self.orderings.as_slice() {
+            [single] => *single,
+            orderings => orderings.get(car).copied().unwrap_or(CallOrdering::Nearest),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.floors < 2 {
+            return Err(format!("building needs at least 2 floors, got {}", self.floors));
+        }
+        if self.cars == 0 {
+            return Err("building needs at least one car".to_string());
+        }
+        if self.capacity < 1 {
+            return Err(format!("car capacity must be positive, got {}", self.capacity));
+        }
+        if self.orderings.len() > 1 && self.orderings.len() != self.cars {
+            return Err(format!(
+                "expected 1 or {} call orderings, got {}",
+                self.cars,
+                self.orderings.len()
+            ));
+        }
+        if self.battery_cars == 0 {
+            return Err("battery must power at least one car".to_string());
+        }
+        if !(self.battery_seconds >= 0.0 && self.battery_seconds.is_finite()) {
+            return Err(format!(
+                "battery capacity must be a non-negative number of seconds, got {}",
+                self.battery_seconds
+            ));
+        }
+        if let Some(&floor) = self.danger_order.iter().find(|&&floor| !self.contains(floor)) {
+            return Err(format!(
+                "danger order floor {floor} is outside floors {}..={}",
+                self.lowest_floor,
+                self.top_floor()
+            ));
+        }
+        if let Some(floor) = self.recall_floor
+            && !self.contains(floor)
+        {
+            return Err(format!(
+                "recall floor {floor} is outside floors {}..={}",
+                self.lowest_floor,
+                self.top_floor()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use crate::building::BuildingConfig;
+use crate::dispatch::{self, DispatchStrategy};
+use crate::scenario::Scenario;
+
+pub struct Options {
+    pub building: BuildingConfig,
+    pub dispatch: Box<dyn DispatchStrategy>,
+    pub seed: Option<u64>,
+    pub batch_ticks: Option<u64>,
+    pub metrics_out: Option<String>,
+    pub scenario: Scenario,
+    pub safety_stop: bool,
+}
+
+pub const USAGE: &str = "usage: elevator-sim [run --ticks N [--no-render]] [--floors N] [--lowest-floor N] \
+[--cars N] [--capacity N] [--recall-floor N] [--danger-order FLOOR[,...]] [--battery-cars N] [--rescue-order sequential|nearest|most-passengers] [--dispatch nearest|least-loaded] [--ordering nearest|collective[,...]] [--seed N] [--metrics-out FILE.csv] [--scenario FILE.toml|FILE.json] \
+[--traffic off|inter-floor|up-peak|down-peak|lunch] [--intensity PERSONS_PER_MINUTE] [--safety-log-only] \
+[--door-obstruction PROBABILITY]";
+
+const DEFAULT_BATCH_TICKS: u64 = 10_000;
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let args: Vec<String> = args.into_iter().collect();
+    let mut scenario = match args.iter().position(|arg| arg == "--scenario") {
+        Some(i) => Scenario::load(&parse_value::<String>("--scenario", args.get(i + 1).cloned())?)?,
+        None => Scenario::default(),
+    };
+    let mut building = scenario.building.clone();
+    let mut strategy = dispatch::strategy_by_name("nearest").unwrap();
+    let mut seed = None;
+    let mut batch = false;
+    let mut ticks = None;
+    let mut metrics_out = None;
+    let mut safety_stop = true;
+
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "run") {
+        args.next();
+        batch = true;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--floors" => building.floors = parse_value(&arg, args.next())?,
+            "--lowest-floor" => building.lowest_floor = parse_value(&arg, args.next())?,
+            "--cars" => building.cars = parse_value(&arg, args.next())?,
+            "--capacity" => building.capacity = parse_value(&arg, args.next())?,
+            "--battery-cars" => building.battery_cars = parse_value(&arg, args.next())?,
+            "--rescue-order" => building.rescue_order = parse_value(&arg, args.next())?,
+            "--recall-floor" => building.recall_floor = Some(parse_value(&arg, args.next())?),
+            "--ordering" => {
+                let value: String = parse_value(&arg, args.next())?;
+                building.orderings = value
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+            }
+            "--danger-order" => {
+                let value: String = parse_value(&arg, args.next())?;
+                building.danger_order = value
+                    .split(',')
+                    .map(|floor| parse_value(&arg, Some(floor.to_string())))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--ticks" => ticks = Some(parse_value(&arg, args.next())?),
+            "--no-render" if batch => {}
+            "--safety-log-only" => safety_stop = false,
+            "--door-obstruction" => scenario.door_obstruction = parse_value(&arg, args.next())?,
+            "--metrics-out" => metrics_out = Some(parse_value(&arg, args.next())?),
+            "--traffic" => scenario.traffic.pattern = parse_value(&arg, args.next())?,
+            "--intensity" => scenario.traffic.intensity = parse_value(&arg, args.next())?,
+            "--scenario" => {
+                args.next();
+            }
+            "--seed" => seed = Some(parse_value(&arg, args.next())?),
+            "--dispatch" => {
+                let name: String = parse_value(&arg, args.next())?;
+                strategy = dispatch::strategy_by_name(&name).ok_or_else(|| {
+                    format!(
+                        "unknown dispatch strategy '{name}', expected one of {}",
+                        dispatch::STRATEGIES.join(", ")
+                    )
+                })?;
+            }
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+
+    building.validate()?;
+    scenario.validate(&building)?;
+    if ticks.is_some() && !batch {
+        return Err("--ticks is only supported by the run command".to_string());
+    }
+    let batch_ticks = batch.then(|| ticks.unwrap_or(DEFAULT_BATCH_TICKS));
+
+    Ok(Options {
+        building,
+        dispatch: strategy,
+        seed,
+        batch_ticks,
+        metrics_out,
+        scenario,
+        safety_stop,
+    })
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for {flag}"))
+}
@@ -0,0 +1,76 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use std::time::Duration;
+
+pub const TICK: Duration = Duration::from_millis(250);
+pub const CAR_STEP: Duration = Duration::from_millis(40);
+
+pub const MIN_TIME_SCALE: f64 = 0.1;
+pub const MAX_TIME_SCALE: f64 = 100.0;
+pub const FAST_FORWARD_SCALE: f64 = 50.0;
+
+struct ClockInner {
+    now_us: AtomicU64,
+    scale_bits: AtomicU64,
+    paused: AtomicBool,
+}
+
+#[derive(Clone)]
+pub struct SimClock {
+    inner: Arc<ClockInner>,
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        SimClock {
+            inner: Arc::new(ClockInner {
+                now_us: AtomicU64::new(0),
+                scale_bits: AtomicU64::new(1.0f64.to_bits()),
+                paused: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        Duration::from_micros(self.inner.now_us.load(Ordering::Acquire))
+    }
+
+    pub fn advance(&self, sim: Duration) {
+        self.inner
+            .now_us
+            .fetch_add(sim.as_micros() as u64, Ordering::AcqRel);
+    }
+
+    pub fn scaled(&self, real: Duration) -> Duration {
+        if self.is_paused() {
+            return Duration::ZERO;
+        }
+        real.mul_f64(self.time_scale())
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        f64::from_bits(self.inner.scale_bits.load(Ordering::Relaxed))
+    }
+
+    pub fn set_time_scale(&self, scale: f64) {
+        let scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.inner
+            .scale_bits
+            .store(scale.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.inner.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn real_interval(&self, sim: Duration) -> Duration {
+        sim.div_f64(self.time_scale())
+            .clamp(Duration::from_millis(1), sim)
+    }
+}
@@ -0,0 +1,130 @@
+use crate::elevator::{CarMode, Direction, State};
+use crate::snapshot::CarSnapshot;
+use crate::elevator_controller::PickupRequest;
+
+pub struct Assignment {
+    pub request: usize,
+    pub elevator: usize,
+}
+
+pub trait DispatchStrategy: Send {
+    fn name(&self) -> &'static str;
+
+    fn assign(&mut self, cars: &[CarSnapshot], requests: &[PickupRequest]) -> Vec<Assignment>;
+}
+
+pub const STRATEGIES: &[&str] = &["nearest", "least-loaded"];
+
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn DispatchStrategy>> {
+    match name {
+        "nearest" => Some(Box::new(NearestCar)),
+        "least-loaded" => Some(Box::new(LeastLoaded)),
+        _ => None,
+    }
+}
+
+fn is_available(car: &CarSnapshot) -> bool {
+    !car.is_full() && !car.halted && car.mode == CarMode::InService
+}
+
+pub struct NearestCar;
+
+impl DispatchStrategy for NearestCar {
+    fn name(&self) -> &'static str {
+        "nearest"
+    }
+
+    fn assign(&mut self, cars: &[CarSnapshot], requests: &[PickupRequest]) -> Vec<Assignment> {
+        let mut assignments = Vec::new();
+        let mut committed = vec![false; cars.len()];
+
+        for (i, req) in requests.iter().enumerate() {
+            if req.assigned_elevator.is_some() {
+                continue;
+            }
+            let floor = req.floor;
+            let mut best_elevator = None;
+            let mut min_distance = i32::MAX;
+
+            for (idx, state) in cars.iter().enumerate() {
+                if !is_available(state) {
+                    continue;
+                }
+
+                if state.serves(floor, req.direction) {
+                    best_elevator = Some(idx);
+                    break;
+                }
+
+                if committed[idx] {
+                    continue;
+                }
+
+                let dist = (state.floor - floor).abs();
+                let is_idle = state.pending_stops == 0 && (state.state == State::Idle);
+
+                if is_idle {
+                    if dist < min_distance {
+                        min_distance = dist;
+                        best_elevator = Some(idx);
+                    }
+                } else if state.state == State::Driving {
+                    let approaching = match state.direction {
+                        Direction::Up => state.floor < floor,
+                        Direction::Down => state.floor > floor,
+                    };
+                    if approaching && dist < min_distance {
+                        min_distance = dist;
+                        best_elevator = Some(idx);
+                    }
+                }
+            }
+
+            if let Some(elevator) = best_elevator {
+                committed[elevator] = true;
+                assignments.push(Assignment { request: i, elevator });
+            }
+        }
+
+        assignments
+    }
+}
+
+pub struct LeastLoaded;
+
+impl DispatchStrategy for LeastLoaded {
+    fn name(&self) -> &'static str {
+        "least-loaded"
+    }
+
+    fn assign(&mut self, cars: &[CarSnapshot], requests: &[PickupRequest]) -> Vec<Assignment> {
+        let mut assignments = Vec::new();
+        let mut load: Vec<usize> = cars
+            .iter()
+            .map(|car| car.pending_stops + car.passenger_count as usize)
+            .collect();
+
+        for (i, req) in requests.iter().enumerate() {
+            if req.assigned_elevator.is_some() {
+                continue;
+            }
+
+            let best_elevator = cars
+                .iter()
+                .enumerate()
+                .filter(|(_, car)| is_available(car))
+                .min_by_key(|(idx, car)| {
+                    let at_floor = car.serves(req.floor, req.direction);
+                    (!at_floor, load[*idx], (car.floor - req.floor).abs())
+                })
+                .map(|(idx, _)| idx);
+
+            if let Some(elevator) = best_elevator {
+                load[elevator] += 1;
+                assignments.push(Assignment { request: i, elevator });
+            }
+        }
+
+        assignments
+    }
+}
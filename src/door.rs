@@ -0,0 +1,226 @@
+use crate::renderer::debug;
+
+const DOOR_SPEED: f32 = 0.05;
+const NUDGING_DOOR_SPEED: f32 = 0.02;
+const NUDGE_AFTER_REOPENS: u32 = 3;
+const DWELL_STEPS: u32 = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+#[derive(Clone, Debug)]
+pub struct Door {
+    pub elevator: usize,
+    pub state: DoorState,
+    pub progress: f32,
+    pub dwell: u32,
+    pub obstruction: u32,
+    pub consecutive_reopens: u32,
+    pub nudging: bool,
+    pub total_reopens: u32,
+    pub stuck: Option<DoorState>,
+    pub powered: bool,
+}
+
+impl Door {
+    pub fn new(elevator: usize) -> Self {
+        Door {
+            elevator,
+            state: DoorState::Open,
+            progress: 1.0,
+            dwell: 0,
+            obstruction: 0,
+            consecutive_reopens: 0,
+            nudging: false,
+            total_reopens: 0,
+            stuck: None,
+            powered: true,
+        }
+    }
+
+    pub fn open(&mut self) {
+        if self.stuck == Some(DoorState::Closed) {
+            return;
+        }
+        if matches!(self.state, DoorState::Closed | DoorState::Closing) {
+            self.state = DoorState::Opening;
+        }
+    }
+
+    pub fn close(&mut self) {
+        if self.stuck == Some(DoorState::Open) {
+            return;
+        }
+        if matches!(self.state, DoorState::Open | DoorState::Opening) {
+            self.state = DoorState::Closing;
+        }
+    }
+
+    pub fn hold(&mut self) {
+        self.dwell = DWELL_STEPS;
+    }
+
+    pub fn jam(&mut self, at: DoorState) {
+        self.stuck = Some(at);
+        match (at, self.state) {
+            (DoorState::Open, DoorState::Closing) => self.state = DoorState::Opening,
+            (DoorState::Closed, DoorState::Opening) => self.state = DoorState::Closing,
+            _ => {}
+        }
+    }
+
+    pub fn obstruct(&mut self, steps: u32) {
+        self.obstruction = self.obstruction.max(steps);
+    }
+
+    pub fn step(&mut self) -> Option<DoorState> {
+        if !self.powered {
+            return None;
+        }
+        let obstructed = self.obstruction > 0;
+        if obstructed {
+            self.obstruction -= 1;
+        }
+
+        match self.state {
+            DoorState::Closed => {}
+            DoorState::Opening => {
+                self.progress += DOOR_SPEED;
+                if self.progress >= 1.0 {
+                    self.progress = 1.0;
+                    self.state = DoorState::Open;
+                    self.dwell = DWELL_STEPS;
+                    return Some(DoorState::Open);
+                }
+            }
+            DoorState::Open => {
+                if self.dwell > 0 {
+                    self.dwell -= 1;
+                }
+            }
+            DoorState::Closing => {
+                if obstructed && !self.nudging && self.stuck.is_none() {
+                    self.consecutive_reopens += 1;
+                    self.total_reopens += 1;
+                    self.state = DoorState::Opening;
+                    debug(format!(
+                        "Elevator {} door obstructed, reopening ({} in a row)",
+                        self.elevator, self.consecutive_reopens
+                    ));
+                    if self.consecutive_reopens >= NUDGE_AFTER_REOPENS {
+                        self.nudging = true;
+                        debug(format!("Elevator {} door nudging closed", self.elevator));
+                    }
+                    return None;
+                }
+                let speed = if self.nudging { NUDGING_DOOR_SPEED } else { DOOR_SPEED };
+                self.progress -= speed;
+                if self.progress <= 0.0 {
+                    self.progress = 0.0;
+                    self.state = DoorState::Closed;
+                    self.consecutive_reopens = 0;
+                    self.nudging = false;
+                    return Some(DoorState::Closed);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed_door() -> Door {
+        let mut door = Door::new(0);
+        door.close();
+        step_until(&mut door, DoorState::Closed);
+        door
+    }
+
+    fn step_until(door: &mut Door, state: DoorState) -> u32 {
+        let mut steps = 0;
+        while door.state != state {
+            door.step();
+            steps += 1;
+            assert!(steps < 1000, "door never reached {state:?}");
+        }
+        steps
+    }
+
+    fn obstruct_while_closing(door: &mut Door) {
+        step_until(door, DoorState::Open);
+        door.close();
+        door.step();
+        door.obstruct(1);
+        door.step();
+    }
+
+    #[test]
+    fn dwell_expires_after_opening() {
+        let mut door = closed_door();
+        door.open();
+
+        step_until(&mut door, DoorState::Open);
+        assert_eq!(door.dwell, DWELL_STEPS);
+        for _ in 0..DWELL_STEPS {
+            assert_eq!(door.step(), None);
+        }
+        assert_eq!(door.dwell, 0);
+        assert_eq!(door.state, DoorState::Open);
+
+        door.hold();
+        assert_eq!(door.dwell, DWELL_STEPS);
+    }
+
+    #[test]
+    fn obstruction_reopens_closing_door() {
+        let mut door = Door::new(0);
+        door.close();
+        door.step();
+        door.obstruct(1);
+
+        assert_eq!(door.step(), None);
+        assert_eq!(door.state, DoorState::Opening);
+        assert_eq!(door.consecutive_reopens, 1);
+        assert_eq!(door.total_reopens, 1);
+        assert!(!door.nudging);
+    }
+
+    #[test]
+    fn door_nudges_closed_after_reopen_limit() {
+        let mut door = Door::new(0);
+        for _ in 0..NUDGE_AFTER_REOPENS {
+            obstruct_while_closing(&mut door);
+        }
+        assert!(door.nudging);
+        assert_eq!(door.consecutive_reopens, NUDGE_AFTER_REOPENS);
+
+        step_until(&mut door, DoorState::Open);
+        door.close();
+        door.obstruct(5);
+        let steps = step_until(&mut door, DoorState::Closed);
+        assert!(steps >= (1.0 / NUDGING_DOOR_SPEED) as u32);
+        assert_eq!(door.total_reopens, NUDGE_AFTER_REOPENS);
+        assert_eq!(door.consecutive_reopens, 0);
+        assert!(!door.nudging);
+    }
+
+    #[test]
+    fn closing_fully_resets_consecutive_reopens() {
+        let mut door = Door::new(0);
+        obstruct_while_closing(&mut door);
+        step_until(&mut door, DoorState::Open);
+        door.close();
+
+        step_until(&mut door, DoorState::Closed);
+        assert_eq!(door.consecutive_reopens, 0);
+        assert_eq!(door.total_reopens, 1);
+    }
+}
@@ -1,11 +1,25 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
-};
-use std::thread;
-use std::time::Duration;
+use serde::Deserialize;
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use crate::building::BuildingConfig;
+use crate::clock::{CAR_STEP, SimClock};
+use crate::door::{Door, DoorState};
+use crate::events::{CarEventKind, EventBus};
 use crate::renderer::debug;
+use crate::safety::SafetyMonitor;
+use crate::snapshot::CarSnapshot;
+use crate::watchdog::Heartbeat;
+
+const ENTRY_OBSTRUCTION_STEPS: u32 = 2;
+const RESCUE_DELAY_STEPS: u32 = 125;
+const RESCUE_SPEED: f32 = 0.01;
+const STOP_GRACE: Duration = Duration::from_secs(1);
+pub const LOCK_PATIENCE: Duration = Duration::from_millis(20);
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Direction {
@@ -13,39 +27,324 @@ pub enum Direction {
     Down,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallOrdering {
+    Nearest,
+    Collective,
+}
+
+impl std::str::FromStr for CallOrdering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(CallOrdering::Nearest),
+            "collective" => Ok(CallOrdering::Collective),
+            _ => Err(format!("unknown call ordering '{s}', expected nearest or collective")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CarMode {
+    InService,
+    Maintenance,
+    OutOfService,
+    #[serde(skip)]
+    FireRecall,
+    #[serde(skip)]
+    Firefighter,
+}
+
+impl CarMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CarMode::InService => "in service",
+            CarMode::Maintenance => "maintenance",
+            CarMode::OutOfService => "out of service",
+            CarMode::FireRecall => "fire recall",
+            CarMode::Firefighter => "firefighter",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CarMode::InService => CarMode::Maintenance,
+            CarMode::Maintenance => CarMode::OutOfService,
+            CarMode::OutOfService => CarMode::InService,
+            CarMode::FireRecall | CarMode::Firefighter => self,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fault {
+    MotorFailure,
+    DoorStuckOpen,
+    DoorStuckClosed,
+    WrongFloor,
+    LostMessages,
+}
+
+impl Fault {
+    pub fn name(self) -> &'static str {
+        match self {
+            Fault::MotorFailure => "motor failure",
+            Fault::DoorStuckOpen => "door stuck open",
+            Fault::DoorStuckClosed => "door stuck closed",
+            Fault::WrongFloor => "position sensor reporting wrong floor",
+            Fault::LostMessages => "lost controller messages",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stepping {
+    Threaded,
+    Lockstep,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum State {
+    Idle,
     Driving,
-    Waiting,
-    Closing,
-    Opening,
+    Stopped,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rescue {
+    Waiting(u32),
+    Moving,
+    Done,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
+    GoTo(i32),
+    OpenDoors,
+    CloseDoors,
+    Hold,
+    Stop,
+    Release,
+    Recall(i32),
+    FireService(bool),
+    FirefighterCall(i32),
+}
+
+impl Command {
+    pub fn is_door_command(self) -> bool {
+        matches!(self, Command::OpenDoors | Command::CloseDoors | Command::Hold)
+    }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct Report {
+    pub elevator: usize,
+    pub command: Command,
+    pub accepted: bool,
+    pub floor: i32,
+    pub state: State,
+    pub door: DoorState,
+}
+
+#[derive(Clone, Debug)]
 pub struct ElevatorState {
+    pub number: usize,
     pub floor: i32,
     pub destination: i32,
     pub requests: Vec<i32>,
     pub floor_progress: f32,
     pub direction: Direction,
+    pub ordering: CallOrdering,
     pub state: State,
-    pub door_progress: f32,
+    pub served: bool,
     pub passenger_count: i32,
-    pub wait_timer: u32,
+    pub capacity: i32,
     pub entry_cooldown: u32,
+    pub halted: bool,
+    pub rescue: Option<Rescue>,
+    pub powered: bool,
+    pub steps: u64,
+    pub faults: Vec<Fault>,
+    pub sensor_error: i32,
+    pub lantern: Option<Direction>,
+    pub fire_service: bool,
 }
 
 impl ElevatorState {
-    fn pick_nearest_destination(&mut self) {
-        if let Some(&nearest) = self
-            .requests
+    fn nearest_request(&self, mut candidates: impl FnMut(i32) -> bool) -> Option<i32> {
+        self.requests
             .iter()
-            .min_by_key(|&&r| (r - self.floor).abs())
-        {
-            self.destination = nearest;
+            .copied()
+            .filter(|&r| candidates(r))
+            .min_by_key(|&r| (r - self.floor).abs())
+    }
+
+    fn pick_next_destination(&mut self) {
+        let next = match self.ordering {
+            CallOrdering::Nearest => self.nearest_request(|_| true),
+            CallOrdering::Collective => {
+                let floor = self.floor;
+                let ahead = match self.direction {
+                    Direction::Up => self.nearest_request(|r| r > floor),
+                    Direction::Down => self.nearest_request(|r| r < floor),
+                };
+                ahead.or_else(|| self.nearest_request(|_| true))
+            }
+        };
+        if let Some(next) = next {
+            self.destination = next;
+        }
+    }
+
+    fn execute(&mut self, door: &mut Door, command: Command) -> bool {
+        match command {
+            Command::GoTo(floor) => {
+                if self.halted || self.fire_service {
+                    return false;
+                }
+                self.add_call(floor);
+            }
+            Command::FirefighterCall(floor) => {
+                if self.halted || !self.fire_service {
+                    return false;
+                }
+                self.add_call(floor);
+            }
+            Command::FireService(on) => self.fire_service = on,
+            Command::Recall(floor) => {
+                if self.halted {
+                    return false;
+                }
+                self.requests.clear();
+                self.requests.push(floor);
+                self.destination = floor;
+            }
+            Command::OpenDoors => {
+                if self.state == State::Driving
+                    || self.floor_progress > 0.0
+                    || (self.halted && self.rescue != Some(Rescue::Done))
+                {
+                    return false;
+                }
+                door.open();
+            }
+            Command::CloseDoors => door.close(),
+            Command::Hold => {
+                if door.state != DoorState::Open {
+                    return false;
+                }
+                door.hold();
+            }
+            Command::Stop => self.halt(),
+            Command::Release => {
+                if !self.halted || !self.powered {
+                    return false;
+                }
+                self.halted = false;
+                self.rescue = None;
+                if self.state == State::Stopped && !self.served {
+                    // The arrival's OpenDoors was refused while halted; announce it again.
+                    self.add_call(self.floor);
+                }
+                debug(format!("Elevator {} released from emergency stop", self.number));
+            }
+        }
+        true
+    }
+
+    fn halt(&mut self) {
+        if !self.halted {
+            self.halted = true;
+            self.rescue = Some(Rescue::Waiting(RESCUE_DELAY_STEPS));
+            debug(format!(
+                "Elevator {} emergency stop at floor {} ({:.2})",
+                self.number, self.floor, self.floor_progress
+            ));
+        }
+    }
+
+    fn add_call(&mut self, floor: i32) {
+        if !self.requests.contains(&floor) {
+            self.requests.push(floor);
+        }
+        self.pick_next_destination();
+        if self.state != State::Driving && self.lantern.is_none() {
+            self.lantern = self.heading();
+        }
+    }
+
+    fn heading(&self) -> Option<Direction> {
+        match self.destination.cmp(&self.floor) {
+            std::cmp::Ordering::Greater => Some(Direction::Up),
+            std::cmp::Ordering::Less => Some(Direction::Down),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn stop(&mut self) -> Option<CarEventKind> {
+        self.state = State::Stopped;
+        self.served = false;
+        self.pick_next_destination();
+        self.lantern = if self.requests.is_empty() { None } else { self.heading() };
+        Some(CarEventKind::ArrivedAtFloor)
+    }
+
+    fn depart(&mut self) -> Option<CarEventKind> {
+        self.pick_next_destination();
+        self.lantern = None;
+        self.state = State::Driving;
+        debug(format!("Elevator on floor {} starts driving", self.floor));
+        Some(CarEventKind::Departed)
+    }
+
+    fn step_rescue(&mut self) -> Option<CarEventKind> {
+        match self.rescue? {
+            Rescue::Waiting(0) => {
+                self.rescue = Some(Rescue::Moving);
+                debug(format!("Elevator {} rescue: moving to nearest floor", self.number));
+            }
+            Rescue::Waiting(steps) => self.rescue = Some(Rescue::Waiting(steps - 1)),
+            Rescue::Moving => {
+                if self.floor_progress >= 0.5 {
+                    self.floor_progress += RESCUE_SPEED;
+                    if self.floor_progress >= 1.0 {
+                        self.floor_progress = 0.0;
+                        match self.direction {
+                            Direction::Up => self.floor += 1,
+                            Direction::Down => self.floor -= 1,
+                        }
+                    }
+                } else {
+                    self.floor_progress = (self.floor_progress - RESCUE_SPEED).max(0.0);
+                }
+                if self.floor_progress == 0.0 {
+                    self.rescue = Some(Rescue::Done);
+                    self.requests.retain(|&x| x != self.floor);
+                    debug(format!("Elevator {} rescue: arrived at floor {}", self.number, self.floor));
+                    return self.stop();
+                }
+            }
+            Rescue::Done => {}
         }
+        None
     }
 
-    pub fn step(&mut self) {
+    pub fn has_fault(&self, fault: Fault) -> bool {
+        self.faults.contains(&fault)
+    }
+
+    pub fn step(&mut self, door: DoorState) -> Option<CarEventKind> {
+        self.steps += 1;
+        if !self.powered {
+            return None;
+        }
+        if self.halted {
+            return self.step_rescue();
+        }
         if self.entry_cooldown > 0 {
             self.entry_cooldown -= 1;
         }
@@ -55,19 +354,23 @@ impl ElevatorState {
                 if self.floor_progress == 0.0 && self.requests.contains(&self.floor) {
                     self.requests.retain(|&x| x != self.floor);
                     if !self.requests.is_empty() {
-                        self.pick_nearest_destination();
+                        self.pick_next_destination();
                     } else {
                         self.destination = self.floor;
                     }
-                    self.state = State::Opening;
-                    return;
+                    return self.stop();
                 }
 
-                if self.floor != self.destination {
-                    if self.destination > self.floor {
-                        self.direction = Direction::Up;
-                    } else {
-                        self.direction = Direction::Down;
+                if self.floor != self.destination || self.floor_progress > 0.0 {
+                    if self.floor_progress == 0.0 {
+                        if self.destination > self.floor {
+                            self.direction = Direction::Up;
+                        } else if self.destination < self.floor {
+                            self.direction = Direction::Down;
+                        }
+                    }
+                    if self.has_fault(Fault::MotorFailure) {
+                        return None;
                     }
 
                     self.floor_progress += 0.05;
@@ -79,121 +382,240 @@ impl ElevatorState {
                         }
                     }
                 } else {
-                    self.state = State::Opening;
+                    return self.stop();
                 }
             }
-            State::Opening => {
-                if self.door_progress < 1.0 {
-                    self.door_progress += 0.05;
-                } else {
-                    self.door_progress = 1.0;
-                    self.state = State::Waiting;
-                    self.wait_timer = 50;
-                }
+            State::Stopped | State::Idle if self.requests.contains(&self.floor) => {
+                self.requests.retain(|&x| x != self.floor);
+                return self.stop();
             }
-            State::Closing => {
-                if self.passenger_count > 2 {
-                    self.state = State::Opening;
-                    return;
+            State::Stopped => {
+                if door == DoorState::Open {
+                    self.served = true;
                 }
-                if self.door_progress > 0.0 {
-                    self.door_progress -= 0.05;
-                } else {
-                    self.door_progress = 0.0;
-                    self.state = State::Driving;
-                    debug(format!("Elevator on floor {} starts driving", self.floor));
-                }
-            }
-            State::Waiting => {
-                if self.passenger_count > 2 {
-                    return;
+                if !self.served {
+                    return None;
                 }
-                if self.destination == self.floor && !self.requests.is_empty() {
-                    self.pick_nearest_destination();
+                if self.requests.is_empty() {
+                    self.state = State::Idle;
+                } else if door == DoorState::Closed {
+                    return self.depart();
                 }
-                if self.wait_timer > 0 {
-                    self.wait_timer -= 1;
-                } else if self.floor != self.destination || !self.requests.is_empty() {
-                    self.state = State::Closing;
+            }
+            State::Idle => {
+                if !self.requests.is_empty() && door == DoorState::Closed {
+                    return self.depart();
                 }
             }
         }
+        None
     }
 }
 
 pub struct Elevator {
     pub number: usize,
-    pub elevator_state: Arc<Mutex<ElevatorState>>,
-    paused: Arc<AtomicBool>,
+    elevator_state: Arc<Mutex<ElevatorState>>,
+    door: Arc<Mutex<Door>>,
+    commands: Sender<Command>,
+    inbox: Arc<Mutex<Receiver<Command>>>,
+    reports: Sender<Report>,
+    events: EventBus,
+    stepping: Stepping,
+    clock: SimClock,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    heartbeats: Vec<Heartbeat>,
+    initial: ElevatorState,
+    safety: Arc<Mutex<SafetyMonitor>>,
 }
 
 impl Elevator {
-    pub fn new(number: usize) -> Self {
+    pub fn new(
+        number: usize,
+        config: &BuildingConfig,
+        stepping: Stepping,
+        clock: &SimClock,
+        reports: Sender<Report>,
+        events: &EventBus,
+        safety: &Arc<Mutex<SafetyMonitor>>,
+    ) -> Self {
         let elevator_state = ElevatorState {
-            floor: 0,
-            destination: 0,
+            number,
+            floor: config.lowest_floor,
+            destination: config.lowest_floor,
             requests: Vec::new(),
             floor_progress: 0.0,
             direction: Direction::Up,
-            state: State::Waiting,
-            door_progress: 1.0,
+            ordering: config.ordering_for(number),
+            state: State::Idle,
+            served: true,
             passenger_count: 0,
-            wait_timer: 0,
+            capacity: config.capacity,
             entry_cooldown: 0,
+            halted: false,
+            rescue: None,
+            powered: true,
+            steps: 0,
+            faults: Vec::new(),
+            sensor_error: 0,
+            lantern: None,
+            fire_service: false,
+        };
+
+        let (commands, inbox) = mpsc::channel();
+        let mut elevator = Elevator {
+            number,
+            elevator_state: Arc::new(Mutex::new(elevator_state.clone())),
+            door: Arc::new(Mutex::new(Door::new(number))),
+            commands,
+            inbox: Arc::new(Mutex::new(inbox)),
+            reports,
+            events: events.clone(),
+            stepping,
+            clock: clock.clone(),
+            running: Arc::new(AtomicBool::new(false)),
+            threads: Vec::new(),
+            heartbeats: Vec::new(),
+            initial: elevator_state,
+            safety: Arc::clone(safety),
         };
+        elevator.start();
+        elevator
+    }
 
-        let shared_state = Arc::new(Mutex::new(elevator_state));
-        let thread_state = Arc::clone(&shared_state);
-        let paused_flag = Arc::new(AtomicBool::new(false));
-        let paused_for_thread = Arc::clone(&paused_flag);
+    pub fn start(&mut self) {
+        if self.stepping != Stepping::Threaded || !self.threads.is_empty() {
+            return;
+        }
+        let number = self.number;
+        self.running = Arc::new(AtomicBool::new(true));
+        self.heartbeats.clear();
 
-        thread::spawn(move || {
-            loop {
-                thread::sleep(Duration::from_millis(40));
-                if paused_for_thread.load(Ordering::Relaxed) {
-                    continue;
-                }
-                {
-                    let mut state = thread_state.lock().unwrap();
-                    state.step();
-                }
+        let cab_state = Arc::clone(&self.elevator_state);
+        let cab_door = Arc::clone(&self.door);
+        let cab_inbox = Arc::clone(&self.inbox);
+        let cab_reports = self.reports.clone();
+        let cab_events = self.events.clone();
+        let cab_safety = Arc::clone(&self.safety);
+        let cab = self.spawn_stepper("cab", move |at| {
+            let mut state = cab_state.lock().unwrap();
+            let mut door = cab_door.lock().unwrap();
+            run_commands(&mut state, &mut door, &cab_inbox, &cab_reports);
+            if let Some(kind) = state.step(door.state) {
+                cab_events.publish(number, state.floor, kind);
             }
+            check_safety(&mut state, &door, &cab_safety, at);
         });
 
-        Elevator {
-            number,
-            elevator_state: shared_state,
-            paused: paused_flag,
-        }
+        let door_state = Arc::clone(&self.door);
+        let door_cab = Arc::clone(&self.elevator_state);
+        let door_events = self.events.clone();
+        let door = self.spawn_stepper("door", move |_| {
+            let reached = door_state.lock().unwrap().step();
+            if let Some(reached) = reached {
+                let floor = door_cab.lock().unwrap().floor;
+                door_events.publish(number, floor, door_event(reached));
+            }
+        });
+
+        self.threads = vec![cab, door];
     }
 
-    pub fn add_request(&self, floor: i32) {
-        let mut es = self.elevator_state.lock().unwrap();
-        if !es.requests.contains(&floor) {
-            es.requests.push(floor);
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        let deadline = Instant::now() + STOP_GRACE;
+        while self.threads.iter().any(|handle| !handle.is_finished()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
         }
-        es.pick_nearest_destination();
+        for handle in self.threads.drain(..) {
+            let name = handle.thread().name().unwrap_or("elevator").to_string();
+            if !handle.is_finished() {
+                debug(format!("Thread {name} did not stop, detaching it"));
+                continue;
+            }
+            if let Err(panic) = handle.join() {
+                debug(format!("Thread {name} panicked: {}", panic_message(&*panic)));
+            }
+        }
+        self.elevator_state.clear_poison();
+        self.door.clear_poison();
+        self.inbox.clear_poison();
+    }
 
-        if es.floor != es.destination {
-            match es.state {
-                State::Waiting => {
-                    es.wait_timer = 50;
-                    es.state = State::Closing;
-                }
-                State::Opening => {
-                    es.wait_timer = 50;
-                }
-                State::Closing => {
-                    es.wait_timer = 0;
+    pub fn heartbeats(&self) -> Vec<u64> {
+        self.heartbeats.iter().map(Heartbeat::count).collect()
+    }
+
+    pub fn has_crashed(&self) -> bool {
+        self.running.load(Ordering::Relaxed) && self.threads.iter().any(JoinHandle::is_finished)
+    }
+
+    pub fn cab(&self) -> MutexGuard<'_, ElevatorState> {
+        self.elevator_state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn door(&self) -> MutexGuard<'_, Door> {
+        self.door.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn try_cab(&self, patience: Duration) -> Option<MutexGuard<'_, ElevatorState>> {
+        try_lock(&self.elevator_state, patience)
+    }
+
+    pub fn try_door(&self, patience: Duration) -> Option<MutexGuard<'_, Door>> {
+        try_lock(&self.door, patience)
+    }
+
+    fn spawn_stepper(
+        &mut self,
+        part: &str,
+        mut step: impl FnMut(Duration) + Send + 'static,
+    ) -> JoinHandle<()> {
+        let clock = self.clock.clone();
+        let running = Arc::clone(&self.running);
+        let heartbeat = Heartbeat::default();
+        self.heartbeats.push(heartbeat.clone());
+        thread::Builder::new()
+            .name(format!("elevator-{}-{part}", self.number))
+            .spawn(move || {
+                let mut stepped_until = clock.now();
+                while running.load(Ordering::Relaxed) {
+                    thread::sleep(clock.real_interval(CAR_STEP));
+                    let now = clock.now();
+                    while stepped_until + CAR_STEP <= now {
+                        stepped_until += CAR_STEP;
+                        step(stepped_until);
+                    }
+                    heartbeat.beat();
                 }
-                State::Driving => { }
-            }
+            })
+            .unwrap()
+    }
+
+    pub fn step(&self, at: Duration) {
+        let mut es = self.cab();
+        let mut door = self.door();
+        run_commands(&mut es, &mut door, &self.inbox, &self.reports);
+        if let Some(reached) = door.step() {
+            self.events.publish(self.number, es.floor, door_event(reached));
+        }
+        if let Some(kind) = es.step(door.state) {
+            self.events.publish(self.number, es.floor, kind);
         }
+        check_safety(&mut es, &door, &self.safety, at);
+    }
+
+    pub fn send(&self, command: Command) {
+        self.commands.send(command).unwrap();
     }
 
     pub fn add_passenger(&self) -> bool {
-        let mut es = self.elevator_state.lock().unwrap();
-        if es.passenger_count >= 2 {
+        let (Some(mut es), Some(mut door)) =
+            (self.try_cab(LOCK_PATIENCE), self.try_door(LOCK_PATIENCE))
+        else {
+            return false;
+        };
+        if es.passenger_count >= es.capacity || es.halted {
             return false;
         }
         if es.entry_cooldown > 0 {
@@ -201,41 +623,204 @@ impl Elevator {
         }
         es.entry_cooldown = 10;
         es.passenger_count += 1;
-        if let State::Waiting = es.state {
-            es.wait_timer = 50;
-        }
 
-        if let State::Closing = es.state {
-            es.state = State::Opening;
+        if door.state == DoorState::Closing {
+            door.obstruct(ENTRY_OBSTRUCTION_STEPS);
         }
+        self.events
+            .publish(self.number, es.floor, CarEventKind::PassengerBoarded);
         true
     }
 
     pub fn remove_passenger(&self) {
-        let mut es = self.elevator_state.lock().unwrap();
+        let (Some(mut es), Some(mut door)) =
+            (self.try_cab(LOCK_PATIENCE), self.try_door(LOCK_PATIENCE))
+        else {
+            return;
+        };
         if es.passenger_count > 0 {
             es.passenger_count -= 1;
         }
-        if let State::Waiting = es.state {
-            es.wait_timer = 50;
+        if door.state == DoorState::Open {
+            door.hold();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        let floor = self
+            .try_cab(LOCK_PATIENCE)
+            .map_or(self.initial.floor, |es| es.floor);
+        self.elevator_state = Arc::new(Mutex::new(self.initial.clone()));
+        self.door = Arc::new(Mutex::new(Door::new(self.number)));
+        let (commands, inbox) = mpsc::channel();
+        self.commands = commands;
+        self.inbox = Arc::new(Mutex::new(inbox));
+        self.events.publish(self.number, floor, CarEventKind::Reset);
+    }
+
+    /// Moves a stopped car onto fresh locks and a fresh inbox, keeping its cab and door state.
+    /// Falls back to `last_seen` when a hung thread still holds the cab.
+    pub fn restart(&mut self, last_seen: &CarSnapshot) {
+        let cab = self.try_cab(LOCK_PATIENCE).map(|es| es.clone());
+        let door = self.try_door(LOCK_PATIENCE).map(|door| door.clone());
+        let cab = cab.unwrap_or_else(|| ElevatorState {
+            floor: last_seen.floor,
+            destination: last_seen.destination,
+            floor_progress: last_seen.floor_progress,
+            direction: last_seen.direction,
+            state: last_seen.state,
+            passenger_count: last_seen.passenger_count,
+            halted: last_seen.halted,
+            rescue: last_seen.rescue,
+            powered: last_seen.powered,
+            steps: last_seen.steps,
+            lantern: last_seen.lantern,
+            ..self.initial.clone()
+        });
+        self.elevator_state = Arc::new(Mutex::new(cab));
+        self.door = Arc::new(Mutex::new(door.unwrap_or_else(|| Door::new(self.number))));
+        let (commands, inbox) = mpsc::channel();
+        self.commands = commands;
+        self.inbox = Arc::new(Mutex::new(inbox));
+    }
+
+    pub fn set_fault(&self, fault: Fault, active: bool) {
+        let (Some(mut es), Some(mut door)) =
+            (self.try_cab(LOCK_PATIENCE), self.try_door(LOCK_PATIENCE))
+        else {
+            debug(format!("Elevator {} is not responding, cannot change faults", self.number));
+            return;
+        };
+        if es.has_fault(fault) == active {
+            return;
+        }
+
+        if active {
+            es.faults.push(fault);
+            match fault {
+                Fault::DoorStuckOpen => door.jam(DoorState::Open),
+                Fault::DoorStuckClosed => door.jam(DoorState::Closed),
+                Fault::WrongFloor => {
+                    es.sensor_error = if es.floor > self.initial.floor { -1 } else { 1 }
+                }
+                Fault::MotorFailure | Fault::LostMessages => {}
+            }
+        } else {
+            es.faults.retain(|&f| f != fault);
+            match fault {
+                Fault::DoorStuckOpen | Fault::DoorStuckClosed => door.stuck = None,
+                Fault::WrongFloor => es.sensor_error = 0,
+                Fault::MotorFailure | Fault::LostMessages => {}
+            }
         }
+        debug(format!(
+            "FAULT elevator {} {}: {}",
+            self.number,
+            if active { "injected" } else { "cleared" },
+            fault.name()
+        ));
     }
 
-    pub fn reset(&self) {
-        let mut es = self.elevator_state.lock().unwrap();
-        es.floor = 0;
-        es.destination = 0;
-        es.requests.clear();
-        es.floor_progress = 0.0;
-        es.direction = Direction::Up;
-        es.state = State::Waiting;
-        es.door_progress = 1.0;
-        es.passenger_count = 0;
-        es.wait_timer = 0;
-        es.entry_cooldown = 0;
+    /// Returns whether switching the power off halted the car.
+    pub fn set_power(&self, on: bool) -> bool {
+        let (Some(mut es), Some(mut door)) =
+            (self.try_cab(LOCK_PATIENCE), self.try_door(LOCK_PATIENCE))
+        else {
+            debug(format!("Elevator {} is not responding, cannot switch power", self.number));
+            return false;
+        };
+        es.powered = on;
+        door.powered = on;
+        if on || es.halted {
+            return false;
+        }
+        es.halted = true;
+        es.rescue = Some(Rescue::Waiting(0));
+        debug(format!(
+            "Elevator {} lost power at floor {} ({:.2})",
+            self.number, es.floor, es.floor_progress
+        ));
+        true
     }
 
-    pub fn set_paused(&self, paused: bool) {
-        self.paused.store(paused, Ordering::Relaxed);
+    pub fn toggle_fault(&self, fault: Fault) {
+        let active = self
+            .try_cab(LOCK_PATIENCE)
+            .is_some_and(|es| es.has_fault(fault));
+        self.set_fault(fault, !active);
     }
+
+    pub fn obstruct_door(&self, steps: u32) {
+        if let Some(mut door) = self.try_door(LOCK_PATIENCE) {
+            door.obstruct(steps);
+        }
+    }
+}
+
+impl Drop for Elevator {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_commands(
+    state: &mut ElevatorState,
+    door: &mut Door,
+    inbox: &Mutex<Receiver<Command>>,
+    reports: &Sender<Report>,
+) {
+    for command in inbox.lock().unwrap().try_iter() {
+        if state.has_fault(Fault::LostMessages) {
+            debug(format!("Elevator {} lost {:?}", state.number, command));
+            continue;
+        }
+        let accepted = state.execute(door, command);
+        let _ = reports.send(Report {
+            elevator: state.number,
+            command,
+            accepted,
+            floor: state.floor,
+            state: state.state,
+            door: door.state,
+        });
+    }
+}
+
+fn check_safety(
+    state: &mut ElevatorState,
+    door: &Door,
+    safety: &Mutex<SafetyMonitor>,
+    at: Duration,
+) {
+    if safety.lock().unwrap().check(&CarSnapshot::new(state, door), at) {
+        state.halt();
+    }
+}
+
+fn door_event(reached: DoorState) -> CarEventKind {
+    if reached == DoorState::Open {
+        CarEventKind::DoorsOpened
+    } else {
+        CarEventKind::DoorsClosed
+    }
+}
+
+fn try_lock<T>(mutex: &Mutex<T>, patience: Duration) -> Option<MutexGuard<'_, T>> {
+    let deadline = Instant::now() + patience;
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => return Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) if Instant::now() >= deadline => return None,
+            Err(TryLockError::WouldBlock) => thread::yield_now(),
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
 }
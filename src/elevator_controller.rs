@@ -1,34 +1,127 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
 use crate::{
-    elevator::{Direction, Elevator, State},
+    building::BuildingConfig,
+    clock::{CAR_STEP, SimClock},
+    dispatch::DispatchStrategy,
+    door::DoorState,
+    events::{CarEvent, CarEventKind, EventBus},
+    elevator::{
+        CarMode, Command, Direction, Elevator, LOCK_PATIENCE, Report, State, Stepping,
+    },
+    power::Battery,
+    safety::SafetyMonitor,
+    snapshot::{CarSnapshot, Snapshot},
+    watchdog::Watchdog,
     debug,
 };
 
-struct PickupRequest {
-    floor: i32,
-    direction: Direction,
-    assigned_elevator: Option<usize>,
+const ACK_TIMEOUT_STEPS: u64 = 50;
+const MAX_COMMAND_ATTEMPTS: u32 = 3;
+const STALL_TIMEOUT_STEPS: u64 = 250;
+
+pub struct PickupRequest {
+    pub floor: i32,
+    pub direction: Direction,
+    pub assigned_elevator: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FireService {
+    Off,
+    PhaseOne,
+    PhaseTwo(usize),
+}
+
+struct PendingCommand {
+    elevator: usize,
+    command: Command,
+    sent_at: u64,
+    attempts: u32,
 }
 
 pub struct ElevatorController {
-    elevators: [Elevator; 3],
+    config: BuildingConfig,
+    elevators: Vec<Elevator>,
     pickup_requests: Vec<PickupRequest>,
+    strategy: Box<dyn DispatchStrategy>,
+    clock: SimClock,
+    stepped_until: Duration,
+    reports: Receiver<Report>,
+    unacked: Mutex<Vec<PendingCommand>>,
+    events: EventBus,
+    car_events: Receiver<CarEvent>,
+    modes: Vec<CarMode>,
+    needs_restart: Vec<bool>,
+    watchdog: Watchdog,
+    last_seen: Mutex<Vec<CarSnapshot>>,
+    last_motion: Vec<Option<(CarSnapshot, u64)>>,
+    fire: FireService,
+    battery: Option<Battery>,
+    evacuating: bool,
 }
 
-const MAX_CAPACITY: i32 = 2;
-
 impl ElevatorController {
-    pub fn new_with_elevators() -> Self {
-        let elevator1 = Elevator::new(0);
-        let elevator2 = Elevator::new(1);
-        let elevator3 = Elevator::new(2);
+    pub fn new(
+        config: &BuildingConfig,
+        strategy: Box<dyn DispatchStrategy>,
+        stepping: Stepping,
+        clock: &SimClock,
+        safety: &Arc<Mutex<SafetyMonitor>>,
+    ) -> Self {
+        let (report_tx, reports) = mpsc::channel();
+        let events = EventBus::default();
+        let car_events = events.subscribe();
+        let elevators: Vec<Elevator> = (0..config.cars)
+            .map(|number| {
+                Elevator::new(number, config, stepping, clock, report_tx.clone(), &events, safety)
+            })
+            .collect();
+        let last_seen = elevators
+            .iter()
+            .map(|elevator| CarSnapshot::new(&elevator.cab(), &elevator.door()))
+            .collect();
 
         ElevatorController {
-            elevators: [elevator1, elevator2, elevator3],
+            config: config.clone(),
+            elevators,
             pickup_requests: Vec::new(),
+            strategy,
+            clock: clock.clone(),
+            stepped_until: clock.now(),
+            reports,
+            unacked: Mutex::new(Vec::new()),
+            events,
+            car_events,
+            modes: vec![CarMode::InService; config.cars],
+            needs_restart: vec![false; config.cars],
+            watchdog: Watchdog::new(config.cars),
+            last_seen: Mutex::new(last_seen),
+            last_motion: vec![None; config.cars],
+            fire: FireService::Off,
+            battery: None,
+            evacuating: false,
         }
     }
 
+    pub fn config(&self) -> &BuildingConfig {
+        &self.config
+    }
+
+    pub fn strategy_name(&self) -> &'static str {
+        self.strategy.name()
+    }
+
+    pub fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
     pub fn request_elevator(&mut self, floor: i32, direction: Direction) {
+        if self.fire != FireService::Off {
+            return;
+        }
         debug(format!(
             "Request on floor {floor} with direction {direction:?}"
         ));
@@ -47,115 +140,591 @@ impl ElevatorController {
         }
     }
 
+    pub fn subscribe(&self) -> Receiver<CarEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn send(&self, elevator: usize, command: Command) {
+        let sent_at = self.last_seen.lock().unwrap()[elevator].steps;
+        self.unacked.lock().unwrap().push(PendingCommand {
+            elevator,
+            command,
+            sent_at,
+            attempts: 1,
+        });
+        self.elevators[elevator].send(command);
+    }
+
+    pub fn emergency_stop(&self, elevator: Option<usize>) {
+        for elevator in self.targets(elevator) {
+            self.send(elevator, Command::Stop);
+        }
+    }
+
+    pub fn release(&self, elevator: Option<usize>) {
+        if self.battery.is_some() {
+            debug("Release refused: mains power is down");
+            return;
+        }
+        for elevator in self.targets(elevator) {
+            self.send(elevator, Command::Release);
+        }
+    }
+
+    fn targets(&self, elevator: Option<usize>) -> Vec<usize> {
+        match elevator {
+            Some(elevator) => vec![elevator],
+            None => (0..self.elevators.len()).collect(),
+        }
+    }
+
+    fn door_pending(&self, elevator: usize) -> bool {
+        self.unacked
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|pending| pending.elevator == elevator && pending.command.is_door_command())
+    }
+
+    fn collect_reports(&mut self) {
+        let unacked = self.unacked.get_mut().unwrap();
+        for report in self.reports.try_iter() {
+            if let Some(i) = unacked
+                .iter()
+                .position(|p| p.elevator == report.elevator && p.command == report.command)
+            {
+                unacked.remove(i);
+            }
+            if !report.accepted {
+                debug(format!(
+                    "Elevator {} refused {:?} at floor {} ({:?}, doors {:?})",
+                    report.elevator, report.command, report.floor, report.state, report.door
+                ));
+            }
+        }
+    }
+
     pub fn get_elevator(&self, idx: i32) -> &Elevator {
         &self.elevators[idx as usize]
     }
 
-    pub fn get_elevators(&self) -> &[Elevator; 3] {
-        &self.elevators
+    pub fn step_elevators_until(&mut self, now: Duration) {
+        while self.stepped_until + CAR_STEP <= now {
+            self.stepped_until += CAR_STEP;
+            for elevator in &self.elevators {
+                elevator.step(self.stepped_until);
+            }
+        }
     }
 
-    pub fn update(&mut self) {
-        let mut handled_indices = Vec::new();
+    pub fn mode(&self, elevator: usize) -> CarMode {
+        let mode = self.modes[elevator];
+        match self.fire {
+            _ if mode == CarMode::OutOfService => mode,
+            FireService::Off => mode,
+            FireService::PhaseTwo(car) if car == elevator => CarMode::Firefighter,
+            _ => CarMode::FireRecall,
+        }
+    }
 
-        for (i, req) in self.pickup_requests.iter_mut().enumerate() {
-            let floor = req.floor;
+    pub fn fire_service(&self) -> FireService {
+        self.fire
+    }
 
-            if let Some(idx) = req.assigned_elevator {
-                let state = self.elevators[idx].elevator_state.lock().unwrap();
-                let at_floor = state.floor == floor
-                    && matches!(state.state, State::Waiting | State::Opening | State::Closing);
-                let full = state.passenger_count >= MAX_CAPACITY;
-                drop(state);
+    pub fn set_fire_service(&mut self, fire: FireService) {
+        if fire == self.fire {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.fire, fire);
+        let recall_floor = self.config.recall_floor();
+        match fire {
+            FireService::Off => debug("Fire alarm reset, resuming normal service"),
+            FireService::PhaseOne => {
+                debug(format!("FIRE ALARM: recalling all cars to floor {recall_floor}"))
+            }
+            FireService::PhaseTwo(car) => {
+                debug(format!("Fire service phase II: elevator {car} under firefighter control"))
+            }
+        }
 
-                if full && !at_floor {
-                    req.assigned_elevator = None;
+        let recalled: Vec<usize> = match (previous, fire) {
+            (_, FireService::Off) | (FireService::PhaseOne, _) => Vec::new(),
+            (FireService::Off, _) => (0..self.elevators.len()).collect(),
+            (FireService::PhaseTwo(car), _) => vec![car],
+        };
+        if fire == FireService::Off {
+            for elevator in 0..self.elevators.len() {
+                if self.modes[elevator] != CarMode::OutOfService {
+                    self.send(elevator, Command::FireService(false));
                 }
             }
+        }
+        if previous == FireService::Off {
+            self.pickup_requests.clear();
+            self.unacked
+                .get_mut()
+                .unwrap()
+                .retain(|pending| !matches!(pending.command, Command::GoTo(_)));
+        }
+        for elevator in recalled {
+            self.recall(elevator);
+        }
+    }
 
-            if req.assigned_elevator.is_none() {
-                let mut best_elevator = None;
-                let mut min_distance = i32::MAX;
+    fn recall(&self, elevator: usize) {
+        if self.modes[elevator] != CarMode::OutOfService {
+            self.send(elevator, Command::FireService(true));
+            self.send(elevator, Command::Recall(self.config.recall_floor()));
+        }
+    }
 
-                for (idx, elevator) in self.elevators.iter().enumerate() {
-                    let state = elevator.elevator_state.lock().unwrap();
-                    if state.passenger_count >= MAX_CAPACITY {
-                        continue;
-                    }
+    pub fn battery(&self) -> Option<&Battery> {
+        self.battery.as_ref()
+    }
 
-                    if state.floor == floor
-                        && matches!(
-                            state.state,
-                            State::Waiting | State::Opening | State::Closing
-                        )
-                    {
-                        best_elevator = Some(idx);
-                        break;
-                    }
+    pub fn power_failure(&mut self) {
+        if self.battery.is_some() {
+            return;
+        }
+        debug("POWER FAILURE: mains power lost");
+        let mut battery = Battery::new(&self.config, self.clock.now());
+        for (i, elevator) in self.elevators.iter().enumerate() {
+            if elevator.set_power(false) {
+                battery.halted.push(i);
+            }
+        }
+        self.battery = Some(battery);
+    }
+
+    pub fn restore_power(&mut self) {
+        let Some(battery) = self.battery.take() else {
+            return;
+        };
+        debug(format!(
+            "Mains power restored after {:.1}s",
+            (self.clock.now() - battery.since).as_secs_f64()
+        ));
+        for elevator in &self.elevators {
+            elevator.set_power(true);
+        }
+        for elevator in battery.halted {
+            self.send(elevator, Command::Release);
+        }
+    }
 
-                    let dist = (state.floor - floor).abs();
-                    let is_idle = state.requests.is_empty() && (state.state == State::Waiting);
+    fn run_battery(&mut self, cars: &[CarSnapshot]) {
+        let now = self.clock.now();
+        let Some(battery) = &mut self.battery else {
+            return;
+        };
+        let was_empty = battery.is_empty();
+        battery.drain(now);
+        if battery.is_empty() && !was_empty {
+            debug("Emergency battery depleted");
+        }
 
-                    if is_idle {
-                        if dist < min_distance {
-                            min_distance = dist;
-                            best_elevator = Some(idx);
-                        }
-                    } else if state.state == State::Driving {
-                        match state.direction {
-                            Direction::Up => {
-                                if state.floor < floor && dist < min_distance {
-                                    min_distance = dist;
-                                    best_elevator = Some(idx);
-                                }
-                            }
-                            Direction::Down => {
-                                if state.floor > floor && dist < min_distance {
-                                    min_distance = dist;
-                                    best_elevator = Some(idx);
-                                }
-                            }
+        for elevator in std::mem::take(&mut battery.powered) {
+            let car = &cars[elevator];
+            if Battery::is_parked(car) {
+                battery.parked[elevator] = true;
+                debug(format!(
+                    "Elevator {elevator} parked on floor {} {:.1}s after the power failure",
+                    car.floor,
+                    (now - battery.since).as_secs_f64()
+                ));
+            } else if !battery.is_empty() {
+                battery.powered.push(elevator);
+                continue;
+            }
+            self.elevators[elevator].set_power(false);
+        }
+
+        while battery.powered.len() < battery.slots && !battery.is_empty() {
+            let Some(elevator) = battery.next_car(cars) else {
+                break;
+            };
+            debug(format!(
+                "Battery power allocated to elevator {elevator} ({:.0}s charge left)",
+                battery.charge.as_secs_f64()
+            ));
+            battery.powered.push(elevator);
+            self.elevators[elevator].set_power(true);
+        }
+    }
+
+    pub fn start_evacuation(&mut self) {
+        if self.evacuating {
+            return;
+        }
+        self.evacuating = true;
+        let exit_floor = self.config.lobby_floor();
+        debug(format!("EVACUATION: all occupants to floor {exit_floor}"));
+        self.pickup_requests.clear();
+        let cars = self.snapshot().cars;
+        for car in cars.iter().filter(|car| car.passenger_count > 0) {
+            self.send(car.number, Command::Recall(exit_floor));
+        }
+    }
+
+    fn dispatch_shuttles(&mut self, cars: &[CarSnapshot]) {
+        let mut busy: Vec<bool> = cars
+            .iter()
+            .map(|car| {
+                car.mode != CarMode::InService
+                    || car.halted
+                    || car.passenger_count > 0
+                    || car.pending_stops > 0
+            })
+            .collect();
+        for req in &self.pickup_requests {
+            if let Some(elevator) = req.assigned_elevator {
+                busy[elevator] = true;
+            }
+        }
+
+        let mut waiting: Vec<usize> = (0..self.pickup_requests.len())
+            .filter(|&i| self.pickup_requests[i].assigned_elevator.is_none())
+            .collect();
+        waiting.sort_by_key(|&i| self.config.danger_rank(self.pickup_requests[i].floor));
+        for i in waiting {
+            let floor = self.pickup_requests[i].floor;
+            let Some(car) = cars
+                .iter()
+                .filter(|car| !busy[car.number])
+                .min_by_key(|car| (car.floor - floor).abs())
+            else {
+                break;
+            };
+            busy[car.number] = true;
+            self.pickup_requests[i].assigned_elevator = Some(car.number);
+            debug(format!("Evacuation shuttle: elevator {} to floor {floor}", car.number));
+            self.send(car.number, Command::GoTo(floor));
+        }
+    }
+
+    pub fn firefighter_call(&self, floor: i32) {
+        match self.fire {
+            FireService::PhaseTwo(car) => self.send(car, Command::FirefighterCall(floor)),
+            _ => debug("No car under firefighter control"),
+        }
+    }
+
+    pub fn set_mode(&mut self, elevator: usize, mode: CarMode) {
+        let previous = std::mem::replace(&mut self.modes[elevator], mode);
+        if previous == mode {
+            return;
+        }
+        debug(format!(
+            "Elevator {elevator} mode: {} -> {}",
+            previous.name(),
+            mode.name()
+        ));
+
+        if mode == CarMode::OutOfService {
+            self.unacked
+                .get_mut()
+                .unwrap()
+                .retain(|pending| pending.elevator != elevator);
+        }
+        if mode != CarMode::InService {
+            for req in self.pickup_requests.iter_mut() {
+                if req.assigned_elevator == Some(elevator) {
+                    req.assigned_elevator = None;
+                }
+            }
+        } else if self.needs_restart[elevator] {
+            let last_seen = self.last_seen.get_mut().unwrap()[elevator];
+            let car = &mut self.elevators[elevator];
+            car.stop();
+            car.restart(&last_seen);
+            car.start();
+            self.watchdog.forget(elevator);
+            self.needs_restart[elevator] = false;
+            self.last_motion[elevator] = None;
+            debug(format!("Elevator {elevator} restarted"));
+        }
+        if previous == CarMode::OutOfService && self.mode(elevator) == CarMode::FireRecall {
+            self.recall(elevator);
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let patience: Vec<_> = self
+            .modes
+            .iter()
+            .map(|&mode| {
+                if mode == CarMode::OutOfService { Duration::ZERO } else { LOCK_PATIENCE }
+            })
+            .collect();
+        let cabs: Vec<_> = self
+            .elevators
+            .iter()
+            .zip(&patience)
+            .map(|(elevator, &patience)| elevator.try_cab(patience))
+            .collect();
+        let doors: Vec<_> = self
+            .elevators
+            .iter()
+            .zip(&patience)
+            .map(|(elevator, &patience)| elevator.try_door(patience))
+            .collect();
+
+        let mut last_seen = self.last_seen.lock().unwrap();
+        for (i, (cab, door)) in cabs.iter().zip(&doors).enumerate() {
+            if let (Some(cab), Some(door)) = (cab, door) {
+                last_seen[i] = CarSnapshot::new(cab, door);
+            }
+            last_seen[i].mode = self.mode(i);
+        }
+        Snapshot {
+            cars: last_seen.clone(),
+        }
+    }
+
+    fn handle_events(&mut self, cars: &[CarSnapshot]) {
+        let events: Vec<CarEvent> = self.car_events.try_iter().collect();
+        for event in events {
+            if self.modes[event.elevator] == CarMode::OutOfService {
+                continue;
+            }
+            let car = &cars[event.elevator];
+            match event.kind {
+                CarEventKind::ArrivedAtFloor => {
+                    self.send(event.elevator, Command::OpenDoors);
+                    if !car.is_full() && !car.halted {
+                        self.pickup_requests.retain(|req| {
+                            req.floor != event.floor
+                                || req.assigned_elevator != Some(event.elevator)
+                                || car.lantern.is_some_and(|lantern| lantern != req.direction)
+                        });
+                    }
+                }
+                CarEventKind::Departed => {
+                    for req in self.pickup_requests.iter_mut() {
+                        if req.floor == event.floor && req.assigned_elevator == Some(event.elevator) {
+                            req.assigned_elevator = None;
                         }
                     }
                 }
+                CarEventKind::PassengerBoarded if car.door == DoorState::Open => {
+                    self.send(event.elevator, Command::Hold);
+                }
+                _ => {}
+            }
+        }
+    }
 
-                if let Some(elevator_idx) = best_elevator {
-                    req.assigned_elevator = Some(elevator_idx);
-                    self.elevators[elevator_idx].add_request(floor);
+    fn command_doors(&mut self, cars: &[CarSnapshot]) {
+        for (elevator, car) in cars.iter().enumerate() {
+            let out_of_service = self.modes[elevator] == CarMode::OutOfService;
+            if car.halted || out_of_service || self.door_pending(elevator) {
+                continue;
+            }
+            let overloaded = car.passenger_count > car.capacity;
+            let command = match car.door {
+                DoorState::Closing if overloaded => Command::OpenDoors,
+                DoorState::Open if overloaded => Command::Hold,
+                DoorState::Open if car.door_dwell == 0 && car.pending_stops > 0 => {
+                    Command::CloseDoors
                 }
+                _ => continue,
+            };
+            self.send(elevator, command);
+        }
+    }
+
+    fn check_heartbeats(&mut self) {
+        for i in 0..self.elevators.len() {
+            if self.modes[i] == CarMode::OutOfService {
+                continue;
+            }
+            let elevator = &mut self.elevators[i];
+            let crashed = elevator.has_crashed();
+            if !crashed && self.watchdog.is_responsive(i, &elevator.heartbeats()) {
+                continue;
+            }
+            if crashed {
+                elevator.stop();
+            }
+            let reason = if crashed { "crashed" } else { "stopped responding" };
+            self.take_out_of_service(i, format!("WATCHDOG elevator {i} {reason}"));
+        }
+    }
+
+    fn take_out_of_service(&mut self, elevator: usize, reason: String) {
+        debug(format!("{reason}, taken out of service"));
+        self.needs_restart[elevator] = true;
+        self.set_mode(elevator, CarMode::OutOfService);
+    }
+
+    fn retry_commands(&mut self, cars: &[CarSnapshot]) {
+        let mut failed = Vec::new();
+        let (elevators, modes) = (&self.elevators, &self.modes);
+        self.unacked.get_mut().unwrap().retain_mut(|pending| {
+            if modes[pending.elevator] == CarMode::OutOfService {
+                return false;
+            }
+            let car = &cars[pending.elevator];
+            if car.steps < pending.sent_at + ACK_TIMEOUT_STEPS {
+                return true;
+            }
+            if pending.attempts >= MAX_COMMAND_ATTEMPTS {
+                failed.push(pending.elevator);
+                return false;
+            }
+            pending.attempts += 1;
+            pending.sent_at = car.steps;
+            debug(format!(
+                "Elevator {} did not acknowledge {:?}, resending (attempt {})",
+                pending.elevator, pending.command, pending.attempts
+            ));
+            elevators[pending.elevator].send(pending.command);
+            true
+        });
+        failed.sort();
+        failed.dedup();
+        for elevator in failed {
+            self.take_out_of_service(
+                elevator,
+                format!("FAULT elevator {elevator} does not acknowledge commands"),
+            );
+        }
+    }
+
+    fn supervise_motion(&mut self, cars: &[CarSnapshot]) {
+        for (elevator, car) in cars.iter().enumerate() {
+            if car.mode == CarMode::OutOfService {
+                self.last_motion[elevator] = None;
+                continue;
+            }
+            let Some((previous, since)) = self.last_motion[elevator] else {
+                self.last_motion[elevator] = Some((*car, car.steps));
+                continue;
+            };
+
+            let jump = car.floor - previous.floor;
+            let expected = |direction| match direction {
+                Direction::Up => 1,
+                Direction::Down => -1,
+            };
+            let plausible = (jump == expected(car.direction) || jump == expected(previous.direction))
+                && car.floor_progress < previous.floor_progress;
+            if jump != 0 && !plausible {
+                self.take_out_of_service(
+                    elevator,
+                    format!(
+                        "FAULT elevator {elevator} position jumped from floor {} to {}",
+                        previous.floor, car.floor
+                    ),
+                );
+                continue;
+            }
+
+            let moved = (car.floor, car.floor_progress, car.state, car.door, car.door_progress, car.door_dwell)
+                != (
+                    previous.floor,
+                    previous.floor_progress,
+                    previous.state,
+                    previous.door,
+                    previous.door_progress,
+                    previous.door_dwell,
+                );
+            let busy = car.state == State::Driving
+                || car.pending_stops > 0
+                || matches!(car.door, DoorState::Opening | DoorState::Closing)
+                || (car.state == State::Stopped && car.door == DoorState::Closed);
+            if moved || !busy || car.halted {
+                self.last_motion[elevator] = Some((*car, car.steps));
+            } else if car.steps >= since + STALL_TIMEOUT_STEPS {
+                self.take_out_of_service(
+                    elevator,
+                    format!(
+                        "FAULT elevator {elevator} stalled at floor {} ({:?}, doors {:?})",
+                        car.floor, car.state, car.door
+                    ),
+                );
             }
+        }
+    }
+
+    fn park_cars(&mut self, cars: &[CarSnapshot]) {
+        let park_floor = self.config.lobby_floor();
+        for (elevator, car) in cars.iter().enumerate() {
+            if car.mode != CarMode::Maintenance
+                || car.halted
+                || car.passenger_count > 0
+                || car.pending_stops > 0
+                || car.state != State::Idle
+            {
+                continue;
+            }
+            if car.floor != park_floor {
+                self.send(elevator, Command::GoTo(park_floor));
+            } else if car.door == DoorState::Open
+                && car.door_dwell == 0
+                && !self.door_pending(elevator)
+            {
+                debug(format!("Elevator {elevator} parked for maintenance on floor {park_floor}"));
+                self.send(elevator, Command::CloseDoors);
+            }
+        }
+    }
 
-            if let Some(elevator_idx) = req.assigned_elevator {
-                let state = self.elevators[elevator_idx].elevator_state.lock().unwrap();
-                let full = state.passenger_count >= MAX_CAPACITY;
-                let elevator_at_floor = state.floor == floor
-                    && matches!(
-                        state.state,
-                        State::Waiting | State::Opening | State::Closing
-                    );
-                drop(state);
+    pub fn update(&mut self) {
+        self.check_heartbeats();
+        self.collect_reports();
+        let snapshot = self.snapshot();
+        let cars = &snapshot.cars;
+        self.retry_commands(cars);
+        self.supervise_motion(cars);
+
+        for req in self.pickup_requests.iter_mut() {
+            if let Some(idx) = req.assigned_elevator {
+                let car = &cars[idx];
+                let at_floor = car.is_at_floor(req.floor);
 
-                if elevator_at_floor && !full {
-                    handled_indices.push(i);
+                if (car.is_full() && !at_floor) || car.halted || car.mode != CarMode::InService {
+                    req.assigned_elevator = None;
                 }
             }
         }
 
-        for i in handled_indices.into_iter().rev() {
-            self.pickup_requests.remove(i);
+        if self.evacuating {
+            self.dispatch_shuttles(cars);
+        } else {
+            for assignment in self.strategy.assign(cars, &self.pickup_requests) {
+                let req = &mut self.pickup_requests[assignment.request];
+                if req.assigned_elevator.is_some() {
+                    continue;
+                }
+                req.assigned_elevator = Some(assignment.elevator);
+                let floor = req.floor;
+                self.send(assignment.elevator, Command::GoTo(floor));
+            }
         }
+
+        self.handle_events(cars);
+        self.command_doors(cars);
+        self.park_cars(cars);
+        self.run_battery(cars);
     }
 
     pub fn reset(&mut self) {
-        for elevator in &self.elevators {
+        for elevator in self.elevators.iter_mut() {
+            elevator.stop();
             elevator.reset();
+            elevator.start();
         }
         self.pickup_requests.clear();
-    }
-
-    pub fn set_paused(&self, paused: bool) {
-        for elevator in &self.elevators {
-            elevator.set_paused(paused);
-        }
+        self.reports.try_iter().for_each(drop);
+        self.car_events.try_iter().for_each(drop);
+        self.unacked.get_mut().unwrap().clear();
+        self.last_motion.iter_mut().for_each(|motion| *motion = None);
+        self.modes.iter_mut().for_each(|mode| *mode = CarMode::InService);
+        self.needs_restart.iter_mut().for_each(|restart| *restart = false);
+        (0..self.elevators.len()).for_each(|car| self.watchdog.forget(car));
+        self.fire = FireService::Off;
+        self.battery = None;
+        self.evacuating = false;
     }
 }
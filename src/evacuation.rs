@@ -0,0 +1,83 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::person::Person;
+
+pub struct FloorClearance {
+    pub floor: i32,
+    pub persons: usize,
+    pub cleared_at: Option<Duration>,
+}
+
+pub struct Evacuation {
+    pub exit_floor: i32,
+    pub started_at: Duration,
+    pub completed_at: Option<Duration>,
+    pub floors: Vec<FloorClearance>,
+}
+
+impl Evacuation {
+    pub fn new(exit_floor: i32, persons: &[Person], now: Duration) -> Self {
+        let mut floors: Vec<FloorClearance> = Vec::new();
+        for person in persons.iter().filter(|p| !p.in_elevator() && p.floor != exit_floor) {
+            match floors.iter_mut().find(|f| f.floor == person.floor) {
+                Some(clearance) => clearance.persons += 1,
+                None => floors.push(FloorClearance {
+                    floor: person.floor,
+                    persons: 1,
+                    cleared_at: None,
+                }),
+            }
+        }
+        floors.sort_by_key(|f| std::cmp::Reverse(f.floor));
+
+        Evacuation {
+            exit_floor,
+            started_at: now,
+            completed_at: None,
+            floors,
+        }
+    }
+
+    pub fn update(&mut self, persons: &[Person], now: Duration) {
+        let elapsed = now - self.started_at;
+        for clearance in self.floors.iter_mut().filter(|f| f.cleared_at.is_none()) {
+            if !persons
+                .iter()
+                .any(|p| !p.in_elevator() && p.floor == clearance.floor)
+            {
+                clearance.cleared_at = Some(elapsed);
+            }
+        }
+        if self.completed_at.is_none() && persons.is_empty() {
+            self.completed_at = Some(elapsed);
+        }
+    }
+}
+
+impl fmt::Display for Evacuation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.completed_at {
+            Some(total) => writeln!(f, "Evacuation time: {:.1}s", total.as_secs_f64())?,
+            None => writeln!(f, "Evacuation time: incomplete")?,
+        }
+        writeln!(f, "Floor clearance:")?;
+        for clearance in &self.floors {
+            match clearance.cleared_at {
+                Some(at) => writeln!(
+                    f,
+                    "  floor {:>3}: {:.1}s ({} persons)",
+                    clearance.floor,
+                    at.as_secs_f64(),
+                    clearance.persons
+                )?,
+                None => writeln!(
+                    f,
+                    "  floor {:>3}: not cleared ({} persons)",
+                    clearance.floor, clearance.persons
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CarEventKind {
+    ArrivedAtFloor,
+    DoorsOpened,
+    DoorsClosed,
+    Departed,
+    PassengerBoarded,
+    Reset,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CarEvent {
+    pub elevator: usize,
+    pub floor: i32,
+    pub kind: CarEventKind,
+}
+
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<CarEvent>>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> Receiver<CarEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn publish(&self, elevator: usize, floor: i32, kind: CarEventKind) {
+        let event = CarEvent {
+            elevator,
+            floor,
+            kind,
+        };
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event).is_ok());
+    }
+}
@@ -7,28 +7,94 @@ use termion::input::TermRead;
 use termion::raw::IntoRawMode;
 use termion::screen::IntoAlternateScreen;
 
-use crate::elevator::State;
-use crate::elevator_controller::ElevatorController;
-use crate::person::Person;
+use crate::clock::FAST_FORWARD_SCALE;
+use crate::elevator::Fault;
+use crate::elevator_controller::FireService;
 use crate::renderer::{DEBUG_SENDER, debug, drain_debug_messages, render};
+use crate::simulation::Simulation;
 
+mod building;
+mod cli;
+mod clock;
+mod dispatch;
+mod door;
 mod elevator;
 mod elevator_controller;
+mod evacuation;
+mod events;
+mod metrics;
 mod person;
+mod power;
 mod renderer;
+mod safety;
+mod scenario;
+mod simulation;
+mod snapshot;
+mod stats;
+mod traffic;
+mod watchdog;
+
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+const SPEED_STEP: f64 = 1.25;
+const INTENSITY_STEP: f64 = 1.25;
 
 enum Event {
     Quit,
     Pause,
     SpeedUp,
     SlowDown,
+    FastForward,
     Reset,
     Manual,
-    Spawn(u32, u32),
+    CycleTraffic,
+    TrafficIntensity(f64),
+    Spawn(i32, i32),
+    CycleMode(usize),
+    ToggleFault(usize, Fault),
+    EmergencyStop(Option<usize>),
+    FireAlarm,
+    PowerFailure,
+    Evacuate,
+    Firefighter(Option<usize>),
+    FirefighterCall(i32),
+}
+
+enum Selection {
+    None,
+    Spawn(i32),
+    CarMode,
+    FaultCar,
+    Fault(usize),
+    Stop,
+    Firefighter,
+    FirefighterCall,
 }
 
 fn main() {
+    let mut options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let metrics_out = options.metrics_out.take();
+
+    if let Some(ticks) = options.batch_ticks {
+        let simulation = run_batch(options, ticks);
+        export_metrics(&simulation, metrics_out.as_deref());
+        return;
+    }
+
+    let config = options.building;
+
     std::panic::set_hook(Box::new(|info| {
+        let thread = std::thread::current();
+        if let Some(name) = thread.name().filter(|name| name.starts_with("elevator-")) {
+            debug(format!("PANIC in {name}: {info}"));
+            return;
+        }
         let mut screen = std::io::stdout();
         write!(screen, "{}", termion::cursor::Show).unwrap();
         write!(screen, "{}", termion::screen::ToMainScreen).unwrap();
@@ -36,7 +102,7 @@ fn main() {
         println!("PANIC: {:?}", info.payload_as_str());
     }));
 
-    {
+    let simulation = {
         let mut screen = stdout()
             .into_raw_mode()
             .unwrap()
@@ -51,23 +117,59 @@ fn main() {
 
         thread::spawn(move || {
             let stdin = stdin();
-            let mut last_num = u32::MAX;
+            let mut selection = Selection::None;
             for c in stdin.keys() {
-                match c.unwrap() {
-                    Key::Char('q') => tx.send(Event::Quit).unwrap(),
-                    Key::Char('+') => tx.send(Event::SpeedUp).unwrap(),
-                    Key::Char('-') => tx.send(Event::SlowDown).unwrap(),
-                    Key::Char(' ') => tx.send(Event::Pause).unwrap(),
-                    Key::Char('r') => tx.send(Event::Reset).unwrap(),
-                    Key::Char('m') => tx.send(Event::Manual).unwrap(),
-                    Key::Char(c @ '0'..='9') => {
-                        let n = c.to_digit(10).unwrap();
-                        if last_num == u32::MAX {
-                            last_num = n;
-                        } else {
-                            tx.send(Event::Spawn(last_num - 1, n - 1)).unwrap();
-                            last_num = u32::MAX;
-                        }
+                let digit = |c: char| c.to_digit(10).unwrap() as usize;
+                match (std::mem::replace(&mut selection, Selection::None), c.unwrap()) {
+                    (Selection::Fault(car), Key::Char(c)) => {
+                        let fault = match c {
+                            'm' => Fault::MotorFailure,
+                            'o' => Fault::DoorStuckOpen,
+                            'c' => Fault::DoorStuckClosed,
+                            's' => Fault::WrongFloor,
+                            'l' => Fault::LostMessages,
+                            _ => continue,
+                        };
+                        tx.send(Event::ToggleFault(car, fault)).unwrap();
+                    }
+                    (_, Key::Char('q')) => tx.send(Event::Quit).unwrap(),
+                    (_, Key::Char('+')) => tx.send(Event::SpeedUp).unwrap(),
+                    (_, Key::Char('-')) => tx.send(Event::SlowDown).unwrap(),
+                    (_, Key::Char('f')) => tx.send(Event::FastForward).unwrap(),
+                    (_, Key::Char(' ')) => tx.send(Event::Pause).unwrap(),
+                    (_, Key::Char('r')) => tx.send(Event::Reset).unwrap(),
+                    (_, Key::Char('m')) => tx.send(Event::Manual).unwrap(),
+                    (_, Key::Char('t')) => tx.send(Event::CycleTraffic).unwrap(),
+                    (_, Key::Char('>')) => tx.send(Event::TrafficIntensity(INTENSITY_STEP)).unwrap(),
+                    (_, Key::Char('<')) => tx.send(Event::TrafficIntensity(1.0 / INTENSITY_STEP)).unwrap(),
+                    (_, Key::Char('c')) => selection = Selection::CarMode,
+                    (_, Key::Char('x')) => selection = Selection::FaultCar,
+                    (_, Key::Char('e')) => selection = Selection::Stop,
+                    (_, Key::Char('F')) => tx.send(Event::FireAlarm).unwrap(),
+                    (_, Key::Char('P')) => tx.send(Event::PowerFailure).unwrap(),
+                    (_, Key::Char('v')) => tx.send(Event::Evacuate).unwrap(),
+                    (_, Key::Char('p')) => selection = Selection::Firefighter,
+                    (_, Key::Char('g')) => selection = Selection::FirefighterCall,
+                    (Selection::Firefighter, Key::Char(c @ '0'..='9')) => {
+                        tx.send(Event::Firefighter(digit(c).checked_sub(1))).unwrap();
+                    }
+                    (Selection::FirefighterCall, Key::Char(c @ '1'..='9')) => {
+                        tx.send(Event::FirefighterCall(digit(c) as i32 - 1)).unwrap();
+                    }
+                    (Selection::Stop, Key::Char(c @ '0'..='9')) => {
+                        tx.send(Event::EmergencyStop(digit(c).checked_sub(1))).unwrap();
+                    }
+                    (Selection::FaultCar, Key::Char(c @ '1'..='9')) => {
+                        selection = Selection::Fault(digit(c) - 1);
+                    }
+                    (Selection::CarMode, Key::Char(c @ '1'..='9')) => {
+                        tx.send(Event::CycleMode(digit(c) - 1)).unwrap();
+                    }
+                    (Selection::Spawn(first), Key::Char(c @ '0'..='9')) => {
+                        tx.send(Event::Spawn(first - 1, digit(c) as i32 - 1)).unwrap();
+                    }
+                    (Selection::None, Key::Char(c @ '0'..='9')) => {
+                        selection = Selection::Spawn(digit(c) as i32);
                     }
                     _ => {}
                 }
@@ -75,18 +177,17 @@ fn main() {
         });
 
         let mut quit = false;
-        const DEFAULT_TICK_DURATION: u64 = 250;
-        let mut tick_duration = Duration::from_millis(DEFAULT_TICK_DURATION);
-        let mut i = 0;
-        let mut pause = false;
-        let mut manual = false;
+        let mut speed_before_fast_forward = None;
 
-        let mut elevator_controller = ElevatorController::new_with_elevators();
-
-        let mut persons = Vec::new();
-        for _ in 0..5 {
-            persons.push(Person::new_rnd());
-        }
+        let mut simulation = Simulation::new(
+            &config,
+            options.dispatch,
+            options.seed,
+            options.scenario,
+            options.safety_stop,
+        );
+        let clock = simulation.clock.clone();
+        let mut last_frame = Instant::now();
 
         loop {
             let start = Instant::now();
@@ -95,119 +196,199 @@ fn main() {
                 match e {
                     Event::Quit => quit = true,
                     Event::Pause => {
-                        pause = !pause;
-                        elevator_controller.set_paused(pause);
+                        let pause = !clock.is_paused();
+                        clock.set_paused(pause);
                         debug(format!("Paused: {}", pause));
                     }
                     Event::SpeedUp => {
-                        if tick_duration.as_millis() > (DEFAULT_TICK_DURATION) as u128 / 2 {
-                            tick_duration -= Duration::from_millis(5)
-                        }
-                        debug(format!("Speeded up simulation"));
+                        clock.set_time_scale(clock.time_scale() * SPEED_STEP);
+                        debug(format!("Speeded up simulation to {:.2}x", clock.time_scale()));
                     }
                     Event::SlowDown => {
-                        if tick_duration.as_millis() < (DEFAULT_TICK_DURATION) as u128 * 2 {
-                            tick_duration += Duration::from_millis(5)
+                        clock.set_time_scale(clock.time_scale() / SPEED_STEP);
+                        debug(format!("Slowed down simulation to {:.2}x", clock.time_scale()));
+                    }
+                    Event::FastForward => {
+                        match speed_before_fast_forward.take() {
+                            Some(scale) => clock.set_time_scale(scale),
+                            None => {
+                                speed_before_fast_forward = Some(clock.time_scale());
+                                clock.set_time_scale(FAST_FORWARD_SCALE);
+                            }
                         }
-                        debug(format!("Slowed down simulation"));
+                        debug(format!("Simulation speed {:.2}x", clock.time_scale()));
                     }
                     Event::Reset => {
-                        elevator_controller.reset();
-                        elevator_controller.set_paused(pause);
-                        persons.clear();
-                        for _ in 0..5 {
-                            persons.push(Person::new_rnd());
-                        }
-                        debug(format!("Reset simulation"));
+                        simulation.reset();
+                        debug("Reset simulation");
+                    }
+                    Event::Manual => {
+                        simulation.manual = !simulation.manual;
+                        debug(format!("Manual mode: {}", simulation.manual));
                     }
-                    Event::Manual => { 
-                        manual = !manual; 
-                        debug(format!("Manual mode: {}", manual));
+                    Event::CycleTraffic => {
+                        let traffic = &mut simulation.traffic;
+                        traffic.pattern = traffic.pattern.next();
+                        debug(format!("Traffic pattern: {}", traffic.pattern.name()));
+                    }
+                    Event::TrafficIntensity(factor) => {
+                        let traffic = &mut simulation.traffic;
+                        traffic.set_intensity(traffic.intensity * factor);
+                        debug(format!("Traffic intensity: {:.1}/min", traffic.intensity));
                     }
                     Event::Spawn(floor, destination) => {
-                        persons.push(Person::new(floor as i32, destination as i32));
+                        let floor = config.lowest_floor + floor;
+                        let destination = config.lowest_floor + destination;
+                        if !config.contains(floor) || !config.contains(destination) {
+                            debug(format!("Cannot spawn person from floor {} to {}", floor, destination));
+                            continue;
+                        }
+                        simulation.spawn_person(floor, destination);
                         debug(format!("Spawned person from floor {} with destination {}", floor, destination));
                     }
+                    Event::CycleMode(car) => {
+                        if car >= config.cars {
+                            debug(format!("No elevator {}", car + 1));
+                            continue;
+                        }
+                        let controller = &mut simulation.controller;
+                        if controller.fire_service() != FireService::Off {
+                            debug("Cannot change car modes during fire service");
+                            continue;
+                        }
+                        controller.set_mode(car, controller.mode(car).next());
+                    }
+                    Event::ToggleFault(car, fault) => {
+                        if car >= config.cars {
+                            debug(format!("No elevator {}", car + 1));
+                            continue;
+                        }
+                        simulation.controller.get_elevator(car as i32).toggle_fault(fault);
+                    }
+                    Event::FireAlarm => {
+                        let controller = &mut simulation.controller;
+                        let fire = match controller.fire_service() {
+                            FireService::Off => FireService::PhaseOne,
+                            _ => FireService::Off,
+                        };
+                        controller.set_fire_service(fire);
+                    }
+                    Event::PowerFailure => {
+                        let controller = &mut simulation.controller;
+                        if controller.battery().is_some() {
+                            controller.restore_power();
+                        } else {
+                            controller.power_failure();
+                        }
+                    }
+                    Event::Evacuate => simulation.start_evacuation(),
+                    Event::Firefighter(car) => {
+                        let controller = &mut simulation.controller;
+                        if controller.fire_service() == FireService::Off {
+                            debug("Phase II requires an active fire alarm");
+                            continue;
+                        }
+                        match car {
+                            Some(car) if car >= config.cars => debug(format!("No elevator {}", car + 1)),
+                            Some(car) => controller.set_fire_service(FireService::PhaseTwo(car)),
+                            None => controller.set_fire_service(FireService::PhaseOne),
+                        }
+                    }
+                    Event::FirefighterCall(floor) => {
+                        let floor = config.lowest_floor + floor;
+                        if !config.contains(floor) {
+                            debug(format!("No floor {floor}"));
+                            continue;
+                        }
+                        simulation.controller.firefighter_call(floor);
+                    }
+                    Event::EmergencyStop(car) => {
+                        if car.is_some_and(|car| car >= config.cars) {
+                            debug(format!("No elevator {}", car.unwrap() + 1));
+                            continue;
+                        }
+                        let controller = &simulation.controller;
+                        let cars = controller.snapshot().cars;
+                        let halted = match car {
+                            Some(car) => cars[car].halted,
+                            None => cars.iter().all(|car| car.halted),
+                        };
+                        if halted {
+                            debug("Releasing emergency stop");
+                            controller.release(car);
+                        } else {
+                            debug("Emergency stop");
+                            controller.emergency_stop(car);
+                        }
+                    }
                 }
             }
             drain_debug_messages(&debug_rx);
             if quit {
                 write!(screen, "{}{}", termion::clear::All, termion::cursor::Show).unwrap();
-                break;
-            }
-            if !pause {
-                update_simulation(&mut elevator_controller, &mut persons, manual);
-
-                render(
-                    &mut screen,
-                    i,
-                    tick_duration.as_millis(),
-                    &elevator_controller,
-                    &persons,
-                );
-                i += 1;
+                break simulation;
             }
+
+            simulation.advance(start - last_frame);
+            last_frame = start;
+
+            render(&mut screen, &simulation);
+
             let elapsed = start.elapsed();
-            if elapsed < tick_duration {
-                std::thread::sleep(tick_duration - elapsed);
+            if elapsed < FRAME_DURATION {
+                std::thread::sleep(FRAME_DURATION - elapsed);
             }
         }
-    }
+    };
+    export_metrics(&simulation, metrics_out.as_deref());
 }
 
-fn update_simulation(controller: &mut ElevatorController, persons: &mut Vec<Person>, manual: bool) {
-    controller.update();
-
-    let mut rng = rand::rng();
-    use rand::Rng;
-    if persons.len() < 30 && rng.random_bool(0.15) && !manual {
-        persons.push(Person::new_rnd());
+fn run_batch(options: cli::Options, ticks: u64) -> Simulation {
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let mut simulation = Simulation::new(
+        &options.building,
+        options.dispatch,
+        Some(seed),
+        options.scenario,
+        options.safety_stop,
+    );
+    for _ in 0..ticks {
+        simulation.tick();
     }
 
-    let mut to_remove = Vec::new();
-
-    for (i, person) in persons.iter_mut().enumerate() {
-        if person.in_elevator {
-            if let Some(elevator_id) = person.elevator_id {
-                let elevator = controller.get_elevator(elevator_id);
-                let state = elevator.elevator_state.lock().unwrap();
-
-                if state.floor == person.destination && matches!(state.state, State::Waiting) {
-                    drop(state);
-                    elevator.remove_passenger();
-
-                    to_remove.push(i);
-                }
-            }
-        } else {
-            let mut entered = false;
-            for elevator in controller.get_elevators() {
-                let state = elevator.elevator_state.lock().unwrap();
-                if state.floor == person.floor
-                    && matches!(
-                        state.state,
-                        State::Waiting | State::Opening | State::Closing
-                    )
-                {
-                    drop(state);
-                    if elevator.add_passenger() {
-                        debug(format!("Added passenger from floor {}", person.floor));
-                        person.in_elevator = true;
-                        person.elevator_id = Some(elevator.number as i32);
-                        elevator.add_request(person.destination);
-                        entered = true;
-                        break;
-                    }
-                }
-            }
-
-            if !entered {
-                person.press_button_up_or_down(controller);
-            }
-        }
+    println!(
+        "Simulated {} ticks ({:.0}s) with seed {}, dispatch {}",
+        simulation.ticks,
+        simulation.clock.now().as_secs_f64(),
+        seed,
+        simulation.controller.strategy_name()
+    );
+    println!("Passengers still in system: {}", simulation.persons.len());
+    print!("{}", simulation.metrics);
+    print!("{}", simulation.stats);
+    if let Some(evacuation) = &simulation.evacuation {
+        print!("{evacuation}");
+    }
+    let safety = simulation.safety.lock().unwrap();
+    println!("Safety violations: {}", safety.violations().len());
+    for violation in safety.violations() {
+        println!("  {violation}");
     }
+    drop(safety);
+    simulation
+}
 
-    for i in to_remove.into_iter().rev() {
-        persons.remove(i);
+fn export_metrics(simulation: &Simulation, path: Option<&str>) {
+    let Some(path) = path else {
+        return;
+    };
+    let result = std::fs::File::create(path)
+        .and_then(|file| simulation.metrics.write_csv(std::io::BufWriter::new(file)));
+    match result {
+        Ok(()) => println!(
+            "Wrote {} journeys to {path}",
+            simulation.metrics.journeys().len()
+        ),
+        Err(err) => eprintln!("Failed to write metrics to {path}: {err}"),
     }
 }
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::person::Person;
+
+#[derive(Clone, Debug)]
+pub struct Journey {
+    pub person: u64,
+    pub origin: i32,
+    pub destination: i32,
+    pub elevator: i32,
+    pub spawned_at: Duration,
+    pub hall_call_at: Option<Duration>,
+    pub boarded_at: Duration,
+    pub alighted_at: Duration,
+    pub trapped: Duration,
+}
+
+impl Journey {
+    pub fn complete(person: &Person, elevator: i32, alighted_at: Duration) -> Option<Self> {
+        Some(Journey {
+            person: person.id,
+            origin: person.origin,
+            destination: person.destination,
+            elevator,
+            spawned_at: person.spawned_at,
+            hall_call_at: person.hall_call_at,
+            boarded_at: person.boarded_at?,
+            alighted_at,
+            trapped: person.trapped,
+        })
+    }
+
+    pub fn wait_time(&self) -> Duration {
+        self.boarded_at - self.spawned_at
+    }
+
+    pub fn ride_time(&self) -> Duration {
+        self.alighted_at - self.boarded_at
+    }
+
+    pub fn time_to_destination(&self) -> Duration {
+        self.alighted_at - self.spawned_at
+    }
+}
+
+#[derive(Default)]
+pub struct MetricsStore {
+    journeys: Vec<Journey>,
+}
+
+impl MetricsStore {
+    pub fn record(&mut self, journey: Journey) {
+        self.journeys.push(journey);
+    }
+
+    pub fn journeys(&self) -> &[Journey] {
+        &self.journeys
+    }
+
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "person,origin,destination,elevator,spawned_at,hall_call_at,boarded_at,alighted_at,wait,ride,time_to_destination,trapped"
+        )?;
+        for j in &self.journeys {
+            writeln!(
+                out,
+                "{},{},{},{},{:.3},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                j.person,
+                j.origin,
+                j.destination,
+                j.elevator,
+                j.spawned_at.as_secs_f64(),
+                j.hall_call_at
+                    .map(|t| format!("{:.3}", t.as_secs_f64()))
+                    .unwrap_or_default(),
+                j.boarded_at.as_secs_f64(),
+                j.alighted_at.as_secs_f64(),
+                j.wait_time().as_secs_f64(),
+                j.ride_time().as_secs_f64(),
+                j.time_to_destination().as_secs_f64(),
+                j.trapped.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn mean(samples: &[Duration]) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    samples.iter().sum::<Duration>() / samples.len() as u32
+}
+
+fn percentile(samples: &[Duration], p: f64) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn write_summary_line(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    samples: &[Duration],
+) -> fmt::Result {
+    writeln!(
+        f,
+        "{label}: avg {:.1}s, p95 {:.1}s",
+        mean(samples).as_secs_f64(),
+        percentile(samples, 95.0).as_secs_f64()
+    )
+}
+
+impl fmt::Display for MetricsStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let waits: Vec<Duration> = self.journeys.iter().map(Journey::wait_time).collect();
+        let rides: Vec<Duration> = self.journeys.iter().map(Journey::ride_time).collect();
+        let totals: Vec<Duration> = self
+            .journeys
+            .iter()
+            .map(Journey::time_to_destination)
+            .collect();
+
+        writeln!(f, "Passengers served: {}", self.journeys.len())?;
+        write_summary_line(f, "Wait time", &waits)?;
+        write_summary_line(f, "Ride time", &rides)?;
+        write_summary_line(f, "Time to destination", &totals)?;
+
+        let trapped: Vec<Duration> = self
+            .journeys
+            .iter()
+            .map(|j| j.trapped)
+            .filter(|trapped| !trapped.is_zero())
+            .collect();
+        writeln!(
+            f,
+            "Trapped: {} passengers, total {:.1}s",
+            trapped.len(),
+            trapped.iter().sum::<Duration>().as_secs_f64()
+        )
+    }
+}
@@ -1,66 +1,360 @@
 use rand::Rng;
+use std::time::Duration;
 
+use crate::building::BuildingConfig;
+use crate::door::DoorState;
+use crate::elevator::{Command, Direction};
 use crate::elevator_controller::ElevatorController;
 
+const BOARDING_TICKS: u32 = 2;
+const CHOOSING_TICKS: u32 = 2;
+const ALIGHTING_TICKS: u32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PassengerState {
+    Idle,
+    Entering { elevator: usize, remaining: u32 },
+    ChoosingDestination { elevator: usize, remaining: u32 },
+    Riding { elevator: usize },
+    Leaving { elevator: usize, remaining: u32 },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PassengerAction {
+    Wait,
+    HoldDoor,
+    Boarded,
+    StepBack,
+    PressButton,
+    Alighted,
+}
+
 pub struct Person {
+    pub id: u64,
+    pub origin: i32,
     pub floor: i32,
     pub destination: i32,
-    pub in_elevator: bool,
-    pub elevator_id: Option<i32>,
+    pub state: PassengerState,
+    pub spawned_at: Duration,
+    pub hall_call_at: Option<Duration>,
+    pub boarded_at: Option<Duration>,
+    pub trapped: Duration,
 }
 
 impl Person {
-    pub fn new(floor: i32, destination: i32) -> Self {
+    pub fn new(id: u64, floor: i32, destination: i32, spawned_at: Duration) -> Self {
         Person {
+            id,
+            origin: floor,
             floor,
             destination,
-            in_elevator: false,
-            elevator_id: None,
+            state: PassengerState::Idle,
+            spawned_at,
+            hall_call_at: None,
+            boarded_at: None,
+            trapped: Duration::ZERO,
         }
     }
 
-    pub fn new_rnd() -> Self {
-        let mut rng = rand::rng();
-
-        let floors = 4;
+    pub fn new_rnd(
+        id: u64,
+        config: &BuildingConfig,
+        rng: &mut impl Rng,
+        spawned_at: Duration,
+    ) -> Self {
+        let floors = config.floor_range();
 
-        let floor = rng.random_range(0..floors);
+        let floor = rng.random_range(floors.clone());
 
-        let mut destination = rng.random_range(0..floors);
+        let mut destination = rng.random_range(floors.clone());
         while destination == floor {
-            destination = rng.random_range(0..floors);
+            destination = rng.random_range(floors.clone());
         }
 
-        Person {
-            floor,
-            destination,
-            in_elevator: false,
-            elevator_id: None,
-        }
+        Person::new(id, floor, destination, spawned_at)
     }
 
     pub fn press_button_up_or_down(&mut self, elevator_controller: &mut ElevatorController) {
+        if self.hall_call_at.is_none() {
+            self.hall_call_at = Some(elevator_controller.clock().now());
+        }
+        elevator_controller.request_elevator(self.floor, self.direction());
+    }
+
+    pub fn direction(&self) -> Direction {
         if self.destination > self.floor {
-            elevator_controller.request_elevator(self.floor, crate::elevator::Direction::Up);
+            Direction::Up
         } else {
-            elevator_controller
-                .request_elevator(self.floor, crate::elevator::Direction::Down);
+            Direction::Down
+        }
+    }
+
+    pub fn elevator(&self) -> Option<usize> {
+        match self.state {
+            PassengerState::Idle => None,
+            PassengerState::Entering { elevator, .. }
+            | PassengerState::ChoosingDestination { elevator, .. }
+            | PassengerState::Riding { elevator }
+            | PassengerState::Leaving { elevator, .. } => Some(elevator),
         }
     }
 
-    pub fn enter_elevator(&mut self, idx: i32, elevator_controller: &mut ElevatorController) {
-        elevator_controller.get_elevator(idx).add_passenger();
-        self.elevator_id = Some(idx);
+    pub fn in_elevator(&self) -> bool {
+        self.state != PassengerState::Idle
     }
 
-    pub fn leave_elevator(&mut self, idx: i32, elevator_controller: &mut ElevatorController) {
-        elevator_controller.get_elevator(idx).remove_passenger();
-        self.elevator_id = None;
+    pub fn advance(&mut self, door: DoorState, now: Duration) -> PassengerAction {
+        match (self.state, door) {
+            (PassengerState::Idle | PassengerState::Riding { .. }, _) => PassengerAction::Wait,
+            (PassengerState::Entering { elevator, remaining }, DoorState::Open) => {
+                if remaining > 1 {
+                    self.state = PassengerState::Entering {
+                        elevator,
+                        remaining: remaining - 1,
+                    };
+                    return PassengerAction::Wait;
+                }
+                self.boarded_at.get_or_insert(now);
+                self.state = PassengerState::ChoosingDestination {
+                    elevator,
+                    remaining: CHOOSING_TICKS,
+                };
+                PassengerAction::Boarded
+            }
+            (PassengerState::Entering { .. }, DoorState::Closed) => {
+                self.state = PassengerState::Idle;
+                PassengerAction::StepBack
+            }
+            (PassengerState::ChoosingDestination { elevator, remaining }, _) => {
+                if remaining > 1 {
+                    self.state = PassengerState::ChoosingDestination {
+                        elevator,
+                        remaining: remaining - 1,
+                    };
+                    return PassengerAction::Wait;
+                }
+                self.state = PassengerState::Riding { elevator };
+                PassengerAction::PressButton
+            }
+            (PassengerState::Leaving { elevator, remaining }, DoorState::Open) => {
+                if remaining > 1 {
+                    self.state = PassengerState::Leaving {
+                        elevator,
+                        remaining: remaining - 1,
+                    };
+                    return PassengerAction::Wait;
+                }
+                self.state = PassengerState::Idle;
+                PassengerAction::Alighted
+            }
+            (PassengerState::Leaving { elevator, .. }, DoorState::Closed) => {
+                self.state = PassengerState::Riding { elevator };
+                PassengerAction::PressButton
+            }
+            (PassengerState::Entering { .. } | PassengerState::Leaving { .. }, DoorState::Closing) => {
+                PassengerAction::HoldDoor
+            }
+            (PassengerState::Entering { .. } | PassengerState::Leaving { .. }, DoorState::Opening) => {
+                PassengerAction::Wait
+            }
+        }
+    }
+
+    pub fn enter_elevator(&mut self, idx: usize, elevator_controller: &ElevatorController) -> bool {
+        if !elevator_controller.get_elevator(idx as i32).add_passenger() {
+            return false;
+        }
+        self.state = PassengerState::Entering {
+            elevator: idx,
+            remaining: BOARDING_TICKS,
+        };
+        true
+    }
+
+    pub fn start_leaving(&mut self, idx: usize) {
+        self.state = PassengerState::Leaving {
+            elevator: idx,
+            remaining: ALIGHTING_TICKS,
+        };
+    }
+
+    pub fn leave_elevator(&self, idx: usize, elevator_controller: &ElevatorController) {
+        elevator_controller.get_elevator(idx as i32).remove_passenger();
+    }
+
+    pub fn press_floor_button(&self, elevator_controller: &ElevatorController) {
+        if let Some(elevator) = self.elevator() {
+            elevator_controller.send(elevator, Command::GoTo(self.destination));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::clock::SimClock;
+    use crate::dispatch;
+    use crate::elevator::Stepping;
+    use crate::safety::SafetyMonitor;
+
+    fn controller() -> ElevatorController {
+        let config = BuildingConfig::default();
+        let safety = Arc::new(Mutex::new(SafetyMonitor::new(&config, true)));
+        ElevatorController::new(
+            &config,
+            dispatch::strategy_by_name("nearest").unwrap(),
+            Stepping::Lockstep,
+            &SimClock::new(),
+            &safety,
+        )
     }
 
-    pub fn press_floor_button(&self, elevator_controller: &mut ElevatorController) {
-        elevator_controller
-            .get_elevator(self.elevator_id.unwrap())
-            .add_request(self.destination);
+    fn person_in(state: PassengerState) -> Person {
+        let mut person = Person::new(1, 0, 3, Duration::ZERO);
+        person.state = state;
+        person
+    }
+
+    fn advance_until(person: &mut Person, door: DoorState, ticks: u32) -> PassengerAction {
+        for _ in 1..ticks {
+            assert_eq!(person.advance(door, Duration::ZERO), PassengerAction::Wait);
+        }
+        person.advance(door, Duration::from_secs(7))
+    }
+
+    #[test]
+    fn passenger_goes_through_every_state() {
+        let controller = controller();
+        let mut person = Person::new(1, 0, 3, Duration::ZERO);
+
+        assert!(person.enter_elevator(0, &controller));
+        assert_eq!(
+            person.state,
+            PassengerState::Entering {
+                elevator: 0,
+                remaining: BOARDING_TICKS
+            }
+        );
+        assert_eq!(controller.snapshot().cars[0].passenger_count, 1);
+
+        assert_eq!(
+            advance_until(&mut person, DoorState::Open, BOARDING_TICKS),
+            PassengerAction::Boarded
+        );
+        assert_eq!(person.boarded_at, Some(Duration::from_secs(7)));
+        assert!(matches!(person.state, PassengerState::ChoosingDestination { elevator: 0, .. }));
+
+        assert_eq!(
+            advance_until(&mut person, DoorState::Closed, CHOOSING_TICKS),
+            PassengerAction::PressButton
+        );
+        assert_eq!(person.state, PassengerState::Riding { elevator: 0 });
+        assert_eq!(person.advance(DoorState::Open, Duration::ZERO), PassengerAction::Wait);
+
+        person.start_leaving(0);
+        assert_eq!(
+            advance_until(&mut person, DoorState::Open, ALIGHTING_TICKS),
+            PassengerAction::Alighted
+        );
+        assert_eq!(person.state, PassengerState::Idle);
+    }
+
+    #[test]
+    fn full_car_refuses_boarding() {
+        let mut controller = controller();
+        let mut first = Person::new(1, 0, 3, Duration::ZERO);
+        let mut second = Person::new(2, 0, 2, Duration::ZERO);
+        let mut third = Person::new(3, 0, 1, Duration::ZERO);
+        let settle = |controller: &mut ElevatorController| {
+            let until = controller.clock().now() + Duration::from_secs(1);
+            controller.step_elevators_until(until);
+        };
+
+        assert!(first.enter_elevator(0, &controller));
+        settle(&mut controller);
+        assert!(second.enter_elevator(0, &controller));
+        settle(&mut controller);
+        assert!(!third.enter_elevator(0, &controller));
+        assert_eq!(third.state, PassengerState::Idle);
+    }
+
+    #[test]
+    fn entering_waits_for_the_door_to_open() {
+        let mut person = person_in(PassengerState::Entering {
+            elevator: 0,
+            remaining: BOARDING_TICKS,
+        });
+
+        for _ in 0..BOARDING_TICKS + 1 {
+            assert_eq!(person.advance(DoorState::Opening, Duration::ZERO), PassengerAction::Wait);
+        }
+        assert_eq!(
+            person.state,
+            PassengerState::Entering {
+                elevator: 0,
+                remaining: BOARDING_TICKS
+            }
+        );
+    }
+
+    #[test]
+    fn closing_door_is_held_by_entering_passenger() {
+        let state = PassengerState::Entering {
+            elevator: 0,
+            remaining: BOARDING_TICKS,
+        };
+        let mut person = person_in(state);
+
+        assert_eq!(person.advance(DoorState::Closing, Duration::ZERO), PassengerAction::HoldDoor);
+        assert_eq!(person.state, state);
+    }
+
+    #[test]
+    fn blocked_entry_steps_back() {
+        let mut person = person_in(PassengerState::Entering {
+            elevator: 0,
+            remaining: 1,
+        });
+
+        assert_eq!(person.advance(DoorState::Closed, Duration::ZERO), PassengerAction::StepBack);
+        assert_eq!(person.state, PassengerState::Idle);
+        assert_eq!(person.boarded_at, None);
+    }
+
+    #[test]
+    fn closing_door_is_held_by_leaving_passenger() {
+        let state = PassengerState::Leaving {
+            elevator: 2,
+            remaining: ALIGHTING_TICKS,
+        };
+        let mut person = person_in(state);
+
+        assert_eq!(person.advance(DoorState::Closing, Duration::ZERO), PassengerAction::HoldDoor);
+        assert_eq!(person.state, state);
+    }
+
+    #[test]
+    fn closed_door_sends_leaving_passenger_back_to_the_button() {
+        let mut person = person_in(PassengerState::Leaving {
+            elevator: 2,
+            remaining: 1,
+        });
+
+        assert_eq!(person.advance(DoorState::Closed, Duration::ZERO), PassengerAction::PressButton);
+        assert_eq!(person.state, PassengerState::Riding { elevator: 2 });
+    }
+
+    #[test]
+    fn boarding_again_keeps_the_first_boarding_time() {
+        let mut person = person_in(PassengerState::Entering {
+            elevator: 1,
+            remaining: 1,
+        });
+        person.boarded_at = Some(Duration::from_secs(3));
+
+        assert_eq!(person.advance(DoorState::Open, Duration::from_secs(9)), PassengerAction::Boarded);
+        assert_eq!(person.boarded_at, Some(Duration::from_secs(3)));
     }
 }
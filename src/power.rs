@@ -0,0 +1,106 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::building::BuildingConfig;
+use crate::elevator::{CarMode, Rescue};
+use crate::snapshot::CarSnapshot;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RescueOrder {
+    Sequential,
+    Nearest,
+    MostPassengers,
+}
+
+impl RescueOrder {
+    pub const ALL: [RescueOrder; 3] = [
+        RescueOrder::Sequential,
+        RescueOrder::Nearest,
+        RescueOrder::MostPassengers,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RescueOrder::Sequential => "sequential",
+            RescueOrder::Nearest => "nearest",
+            RescueOrder::MostPassengers => "most-passengers",
+        }
+    }
+}
+
+impl std::str::FromStr for RescueOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|o| o.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|o| o.name()).collect();
+                format!("unknown rescue order '{s}', expected one of {}", names.join(", "))
+            })
+    }
+}
+
+pub struct Battery {
+    pub charge: Duration,
+    pub slots: usize,
+    pub order: RescueOrder,
+    pub powered: Vec<usize>,
+    pub parked: Vec<bool>,
+    /// Cars the power failure halted; only these are released on restore.
+    pub halted: Vec<usize>,
+    pub since: Duration,
+    drained_until: Duration,
+}
+
+impl Battery {
+    pub fn new(config: &BuildingConfig, now: Duration) -> Self {
+        Battery {
+            charge: Duration::from_secs_f64(config.battery_seconds),
+            slots: config.battery_cars,
+            order: config.rescue_order,
+            powered: Vec::new(),
+            parked: vec![false; config.cars],
+            halted: Vec::new(),
+            since: now,
+            drained_until: now,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.charge.is_zero()
+    }
+
+    pub fn drain(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.drained_until);
+        self.drained_until = now;
+        self.charge = self
+            .charge
+            .saturating_sub(elapsed * self.powered.len() as u32);
+    }
+
+    pub fn next_car(&self, cars: &[CarSnapshot]) -> Option<usize> {
+        let waiting = cars.iter().filter(|car| {
+            car.rescue.is_some()
+                && car.mode != CarMode::OutOfService
+                && !self.parked[car.number]
+                && !self.powered.contains(&car.number)
+        });
+        let distance = |car: &&CarSnapshot| {
+            let progress = car.floor_progress;
+            (progress.min(1.0 - progress) * 1000.0) as u32
+        };
+        let next = match self.order {
+            RescueOrder::Sequential => waiting.min_by_key(|car| car.number),
+            RescueOrder::Nearest => waiting.min_by_key(distance),
+            RescueOrder::MostPassengers => waiting.max_by_key(|car| car.passenger_count),
+        };
+        next.map(|car| car.number)
+    }
+
+    pub fn is_parked(car: &CarSnapshot) -> bool {
+        car.rescue == Some(Rescue::Done) && car.door_progress >= 1.0
+    }
+}
@@ -2,83 +2,178 @@ use chrono::Local;
 use std::collections::VecDeque;
 use std::io::{Stdout, Write};
 use std::sync::{Mutex, OnceLock, mpsc};
+use termion::color;
 use termion::raw::RawTerminal;
 use termion::screen::AlternateScreen;
 
-use crate::elevator::{Direction, Elevator, State};
-use crate::elevator_controller::ElevatorController;
+use crate::building::BuildingConfig;
+use crate::door::DoorState;
+use crate::elevator::{CarMode, Direction, Rescue, State};
+use crate::elevator_controller::FireService;
 use crate::person::Person;
+use crate::simulation::Simulation;
+use crate::snapshot::CarSnapshot;
 
 pub static DEBUG_SENDER: OnceLock<mpsc::Sender<String>> = OnceLock::new();
 static DEBUG_BUFFER: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
 const DEBUG_START_X: u16 = 100;
 const DEBUG_START_Y: u16 = 5;
 const DEBUG_MAX_LINES: usize = 50;
+const LANE_WIDTH: usize = 21;
+const WALL_OFFSET: usize = 3;
+const HALL_WIDTH: usize = 8;
+const MIN_FLOOR_HEIGHT: u16 = 6;
+const MAX_FLOOR_HEIGHT: u16 = 15;
 
 fn safe_goto(x: u16, y: u16) -> termion::cursor::Goto {
     termion::cursor::Goto(std::cmp::max(1, x), std::cmp::max(1, y))
 }
 
-pub fn render(
-    screen: &mut AlternateScreen<RawTerminal<Stdout>>,
-    i: i32,
-    tick_duration: u128,
-    ec: &ElevatorController,
-    persons: &Vec<Person>,
-) {
-    let tick_speed = 1.0 / (tick_duration as f64 / 1000.0);
+pub fn render(screen: &mut AlternateScreen<RawTerminal<Stdout>>, sim: &Simulation) {
+    let ec = &sim.controller;
+    let persons = &sim.persons;
+    let clock = ec.clock();
     write!(
         screen,
-        "{}{}Tick speed {:.2}Hz Ticks {i}",
+        "{}{}Time {:.1}s Speed {:.2}x{} Ticks {} Served {} Dispatch {} Traffic {} {:.1}/min Safety violations {}",
         termion::clear::All,
         safe_goto(1, 1),
-        tick_speed
+        clock.now().as_secs_f64(),
+        clock.time_scale(),
+        if clock.is_paused() { " PAUSED" } else { "" },
+        sim.ticks,
+        sim.metrics.journeys().len(),
+        ec.strategy_name(),
+        sim.traffic.pattern.name(),
+        sim.traffic.intensity,
+        sim.safety.lock().unwrap().violations().len()
     )
     .unwrap();
 
+    let config = ec.config();
+    match ec.fire_service() {
+        FireService::Off => {}
+        FireService::PhaseOne => write!(
+            screen,
+            "{}{}FIRE SERVICE PHASE I: all cars recalled to floor {}{}",
+            safe_goto(1, 3),
+            color::Fg(color::Red),
+            config.recall_floor(),
+            color::Fg(color::Reset)
+        )
+        .unwrap(),
+        FireService::PhaseTwo(car) => write!(
+            screen,
+            "{}{}FIRE SERVICE PHASE II: elevator {car} under firefighter control{}",
+            safe_goto(1, 3),
+            color::Fg(color::Red),
+            color::Fg(color::Reset)
+        )
+        .unwrap(),
+    }
+    if let Some(evacuation) = &sim.evacuation {
+        let elapsed = evacuation
+            .completed_at
+            .unwrap_or_else(|| clock.now() - evacuation.started_at);
+        let cleared = evacuation.floors.iter().filter(|f| f.cleared_at.is_some()).count();
+        write!(
+            screen,
+            "{}{}EVACUATION to floor {}: {} persons remaining, {}/{} floors cleared, {:.1}s{}",
+            safe_goto(1, 2),
+            color::Fg(color::Red),
+            evacuation.exit_floor,
+            persons.len(),
+            cleared,
+            evacuation.floors.len(),
+            elapsed.as_secs_f64(),
+            color::Fg(color::Reset)
+        )
+        .unwrap();
+    }
+    if let Some(battery) = ec.battery() {
+        write!(
+            screen,
+            "{}{}POWER FAILURE: battery {:.0}s left, {} of {} cars on battery, order {}{}",
+            safe_goto(1, 4),
+            color::Fg(color::Yellow),
+            battery.charge.as_secs_f64(),
+            battery.powered.len(),
+            battery.slots,
+            battery.order.name(),
+            color::Fg(color::Reset)
+        )
+        .unwrap();
+    }
     let start_line = 5;
-    let floors = 4;
-    let floor_height = 15;
-
-    draw_building(screen, start_line, floors, floor_height);
-
-    for elevator in ec.get_elevators() {
+    let floors = config.floors as u16;
+    let floor_height = floor_height_for(start_line, floors);
+    let snapshot = ec.snapshot();
+    let cars = snapshot.cars.len();
+
+    draw_building(screen, start_line, floors, floor_height, cars);
+
+    for car in &snapshot.cars {
+        match car.mode {
+            CarMode::InService => {}
+            CarMode::FireRecall | CarMode::Firefighter => {
+                write!(screen, "{}", color::Fg(color::Red)).unwrap()
+            }
+            _ => write!(screen, "{}", color::Fg(color::LightBlack)).unwrap(),
+        }
         draw_elevator(
             screen,
-            elevator,
-            floors as i32,
+            car,
+            config.top_floor(),
             start_line,
             floor_height,
             persons,
         );
+        write!(screen, "{}", color::Fg(color::Reset)).unwrap();
     }
 
-    draw_floors(screen, start_line, floors, floor_height);
+    draw_floors(screen, start_line, floors, floor_height, cars);
 
-    draw_persons_on_floor(screen, persons, start_line, floor_height);
+    draw_persons_on_floor(screen, persons, config, cars, start_line, floor_height);
 
-    draw_debug_area(screen);
+    draw_debug_area(screen, std::cmp::max(DEBUG_START_X, building_width(cars) as u16 + 4));
 
     screen.flush().unwrap();
 }
 
+fn floor_height_for(start_line: u16, floors: u16) -> u16 {
+    let rows = termion::terminal_size().map(|(_, rows)| rows).unwrap_or(u16::MAX);
+    let available = rows.saturating_sub(start_line + 1) / floors.max(1);
+    available.clamp(MIN_FLOOR_HEIGHT, MAX_FLOOR_HEIGHT)
+}
+
+fn building_width(cars: usize) -> usize {
+    1 + cars * LANE_WIDTH + HALL_WIDTH
+}
+
 fn draw_building(
     screen: &mut AlternateScreen<RawTerminal<Stdout>>,
     start_line: u16,
     floors: u16,
     floor_height: u16,
+    cars: usize,
 ) {
+    let ceiling = "-".repeat(building_width(cars));
+    let shafts = format!(
+        "|{}{}|",
+        format!("{}|", " ".repeat(LANE_WIDTH - 1)).repeat(cars),
+        " ".repeat(HALL_WIDTH - 1)
+    );
     for floor in 0..floors {
         write!(
             screen,
-            "{}------------------------------------------------------------------------",
+            "{}{ceiling}",
             safe_goto(1, start_line + (floor_height * floor))
         )
         .unwrap();
         for i in 0..floor_height {
             write!(
                 screen,
-                "{}|                    |                    |                    |       |",
+                "{}{shafts}",
                 safe_goto(1, start_line + 1 + i + (floor_height * floor))
             )
             .unwrap();
@@ -86,7 +181,7 @@ fn draw_building(
     }
     write!(
         screen,
-        "{}------------------------------------------------------------------------",
+        "{}{ceiling}",
         safe_goto(1, start_line + floors * floor_height)
     )
     .unwrap();
@@ -94,27 +189,26 @@ fn draw_building(
 
 fn draw_elevator(
     screen: &mut AlternateScreen<RawTerminal<Stdout>>,
-    elevator: &Elevator,
-    floors: i32,
+    car: &CarSnapshot,
+    top_floor: i32,
     start_line: u16,
     floor_height: u16,
     persons: &[Person],
 ) {
-    let elevator_state = elevator.elevator_state.lock().unwrap();
-    let dir = match elevator_state.direction {
+    let dir = match car.direction {
         Direction::Up => -1,
         Direction::Down => 1,
     };
 
-    let lane_width = 21;
-    let wall_offset = 3;
+    let lane_width = LANE_WIDTH;
+    let wall_offset = WALL_OFFSET;
 
-    let mut x = (elevator.number * lane_width + wall_offset) as u16;
+    let mut x = (car.number * lane_width + wall_offset) as u16;
 
     let start = start_line;
-    let floor_offset = ((floors - 1) - elevator_state.floor) * floor_height as i32;
+    let floor_offset = (top_floor - car.floor) * floor_height as i32;
     let progress_offset =
-        (f32::from(floor_height) * elevator_state.floor_progress * dir as f32).round() as i32;
+        (f32::from(floor_height) * car.floor_progress * dir as f32).round() as i32;
 
     let y_base = start as i32 + 1 + floor_offset + progress_offset;
     if y_base < 1 {
@@ -125,20 +219,50 @@ fn draw_elevator(
     let elevator_height = floor_height - 2;
 
     write!(screen, "{}------------------", safe_goto(x, y)).unwrap();
-    match elevator_state.direction {
+    match car.lantern {
+        Some(Direction::Up) => write!(screen, "{}/\\", safe_goto(x + 8, y)).unwrap(),
+        Some(Direction::Down) => write!(screen, "{}\\/", safe_goto(x + 8, y)).unwrap(),
+        None => {}
+    }
+    match car.direction {
         Direction::Up => write!(screen, "{}|       UP       |", safe_goto(x, y + 1)).unwrap(),
         Direction::Down => write!(screen, "{}|      DOWN      |", safe_goto(x, y + 1)).unwrap(),
     }
 
-    match elevator_state.state {
-        State::Driving => write!(screen, "{}|    Driving     |", safe_goto(x, y + 1)).unwrap(),
-        State::Closing => write!(screen, "{}|    Closing     |", safe_goto(x, y + 1)).unwrap(),
-        State::Opening => write!(screen, "{}|    Opening     |", safe_goto(x, y + 1)).unwrap(),
-        State::Waiting => write!(screen, "{}|     Waiting    |", safe_goto(x, y + 1)).unwrap(),
+    match (car.state, car.door) {
+        (State::Driving, _) => write!(screen, "{}|    Driving     |", safe_goto(x, y + 1)).unwrap(),
+        (_, DoorState::Closing) => write!(screen, "{}|    Closing     |", safe_goto(x, y + 1)).unwrap(),
+        (_, DoorState::Opening) => write!(screen, "{}|    Opening     |", safe_goto(x, y + 1)).unwrap(),
+        (_, DoorState::Open) => write!(screen, "{}|     Waiting    |", safe_goto(x, y + 1)).unwrap(),
+        (State::Stopped, DoorState::Closed) => write!(screen, "{}|    Stopped     |", safe_goto(x, y + 1)).unwrap(),
+        (_, DoorState::Closed) => write!(screen, "{}|      Idle      |", safe_goto(x, y + 1)).unwrap(),
+    }
+
+    if car.door_nudging {
+        write!(screen, "{}|    Nudging     |", safe_goto(x, y + 1)).unwrap();
+    }
+    if car.halted {
+        write!(screen, "{}|     HALTED     |", safe_goto(x, y + 1)).unwrap();
+    }
+    if !car.powered {
+        write!(screen, "{}|    NO POWER    |", safe_goto(x, y + 1)).unwrap();
+    }
+    if car.rescue == Some(Rescue::Moving) {
+        write!(screen, "{}|     RESCUE     |", safe_goto(x, y + 1)).unwrap();
+    }
+    match car.mode {
+        CarMode::InService => {}
+        CarMode::Maintenance => write!(screen, "{}|  MAINTENANCE   |", safe_goto(x, y + 1)).unwrap(),
+        CarMode::OutOfService => write!(screen, "{}| OUT OF SERVICE |", safe_goto(x, y + 1)).unwrap(),
+        CarMode::FireRecall => write!(screen, "{}|  FIRE RECALL   |", safe_goto(x, y + 1)).unwrap(),
+        CarMode::Firefighter => write!(screen, "{}|  FIREFIGHTER   |", safe_goto(x, y + 1)).unwrap(),
+    }
+    if car.faulted {
+        write!(screen, "{}|     FAULT      |", safe_goto(x, y + 1)).unwrap();
     }
 
-    write!(screen, "{}{}", safe_goto(x+15, y + 1), elevator_state.destination).unwrap();
-    write!(screen, "{}|  {}", safe_goto(x, y + 1), elevator_state.floor).unwrap();
+    write!(screen, "{}{}", safe_goto(x+15, y + 1), car.destination).unwrap();
+    write!(screen, "{}|  {}", safe_goto(x, y + 1), car.floor).unwrap();
     write!(screen, "{}------------------", safe_goto(x, y + 2)).unwrap();
 
     let door_patterns = [
@@ -152,7 +276,7 @@ fn draw_elevator(
         "||              ||",
     ];
 
-    let idx = (elevator_state.door_progress * 7.999).floor() as usize;
+    let idx = (car.door_progress * 7.999).floor() as usize;
     let idx = std::cmp::min(idx, door_patterns.len() - 1);
     let door_pattern = door_patterns[idx];
 
@@ -168,23 +292,26 @@ fn draw_elevator(
     .unwrap();
 
     let elevator_wall_offset = 3;
-    let elevator_ceiling_offset = 4;
+    let elevator_ceiling_offset = 4.min(elevator_height - 1);
 
     let passengers_in_this_elevator: Vec<&Person> = persons
         .iter()
-        .filter(|p| p.in_elevator && p.elevator_id == Some(elevator.number as i32))
+        .filter(|p| p.elevator() == Some(car.number))
         .collect();
 
     for (i, person) in passengers_in_this_elevator.iter().enumerate() {
+        if elevator_ceiling_offset + (i / 6) as u16 >= elevator_height {
+            continue;
+        }
         let person_placement = elevator_wall_offset + (i % 6) * 2;
-        x = elevator.number as u16 * lane_width as u16
+        x = car.number as u16 * lane_width as u16
             + wall_offset as u16
             + person_placement as u16;
         let y_pos = start as i32
             + 1
             + floor_offset
             + progress_offset
-            + elevator_ceiling_offset
+            + elevator_ceiling_offset as i32
             + (i / 6) as i32;
         if y_pos < 1 {
             continue;
@@ -204,18 +331,20 @@ fn draw_floors(
     start_line: u16,
     floors: u16,
     floor_height: u16,
+    cars: usize,
 ) {
+    let ceiling = "-".repeat(building_width(cars));
     for floor in (0..floors).rev() {
         write!(
             screen,
-            "{}------------------------------------------------------------------------",
+            "{}{ceiling}",
             safe_goto(1, start_line + (floor_height * floor))
         )
         .unwrap();
     }
     write!(
         screen,
-        "{}------------------------------------------------------------------------",
+        "{}{ceiling}",
         safe_goto(1, start_line + floors * floor_height)
     )
     .unwrap();
@@ -224,29 +353,29 @@ fn draw_floors(
 fn draw_persons_on_floor(
     screen: &mut AlternateScreen<RawTerminal<Stdout>>,
     persons: &Vec<Person>,
+    config: &BuildingConfig,
+    cars: usize,
     start_line: u16,
     floor_height: u16,
 ) {
-    let mut persons_floor_counts = [0u16; 10];
+    let mut persons_floor_counts = vec![0u16; config.floors as usize];
 
-    let mut x: u16 = 0;
-    let mut y: u16 = 0;
-
-    let hall_offset = 66;
-    let floor_roof_offset = 5;
+    let hall_offset = (cars * LANE_WIDTH + WALL_OFFSET) as u16;
+    let floor_roof_offset = 5.min(floor_height - 1);
 
     for person in persons {
-        if !person.in_elevator {
-            if person.floor >= persons_floor_counts.len() as i32 {
-                continue;
-            }
-            x = hall_offset + (persons_floor_counts[3 - person.floor as usize] % 3) * 2;
-            y = start_line
-                + floor_height * (3 - person.floor) as u16
-                + floor_roof_offset
-                + (persons_floor_counts[3 - person.floor as usize] / 3);
-            persons_floor_counts[3 - person.floor as usize] += 1;
+        if person.in_elevator() || !config.contains(person.floor) {
+            continue;
+        }
+        let row = (config.top_floor() - person.floor) as usize;
+        let count = persons_floor_counts[row];
+        let y_offset = floor_roof_offset + count / 3;
+        persons_floor_counts[row] += 1;
+        if y_offset >= floor_height {
+            continue;
         }
+        let x = hall_offset + (count % 3) * 2;
+        let y = start_line + floor_height * row as u16 + y_offset;
 
         write!(screen, "{}{}", safe_goto(x, y), person.destination).unwrap();
     }
@@ -269,10 +398,10 @@ pub fn drain_debug_messages(rx: &mpsc::Receiver<String>) {
     }
 }
 
-fn draw_debug_area(screen: &mut AlternateScreen<RawTerminal<Stdout>>) {
+fn draw_debug_area(screen: &mut AlternateScreen<RawTerminal<Stdout>>, x: u16) {
     let buffer = DEBUG_BUFFER.lock().unwrap();
     for (idx, line) in buffer.iter().enumerate() {
         let y = DEBUG_START_Y + idx as u16;
-        write!(screen, "{}{}", safe_goto(DEBUG_START_X, y), line).unwrap();
+        write!(screen, "{}{}", safe_goto(x, y), line).unwrap();
     }
 }
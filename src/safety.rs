@@ -0,0 +1,205 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::building::BuildingConfig;
+use crate::debug;
+use crate::clock::TICK;
+use crate::elevator::{Direction, State};
+use crate::snapshot::CarSnapshot;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SafetyRule {
+    DoorsOpenWhileDriving,
+    DownwardAtLowestFloor,
+    UpwardAtTopFloor,
+}
+
+impl SafetyRule {
+    const ALL: [SafetyRule; 3] = [
+        SafetyRule::DoorsOpenWhileDriving,
+        SafetyRule::DownwardAtLowestFloor,
+        SafetyRule::UpwardAtTopFloor,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            SafetyRule::DoorsOpenWhileDriving => "S1",
+            SafetyRule::DownwardAtLowestFloor => "S2",
+            SafetyRule::UpwardAtTopFloor => "S3",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SafetyRule::DoorsOpenWhileDriving => "doors open while driving",
+            SafetyRule::DownwardAtLowestFloor => "downward motion at lowest floor",
+            SafetyRule::UpwardAtTopFloor => "upward motion at top floor",
+        }
+    }
+
+    fn is_violated(self, car: &CarSnapshot, lowest_floor: i32, top_floor: i32) -> bool {
+        let moving = car.floor_progress > 0.0;
+        match self {
+            SafetyRule::DoorsOpenWhileDriving => {
+                car.door_progress > 0.0 && (car.state == State::Driving || moving)
+            }
+            SafetyRule::DownwardAtLowestFloor => {
+                car.floor < lowest_floor
+                    || (car.floor == lowest_floor && moving && car.direction == Direction::Down)
+            }
+            SafetyRule::UpwardAtTopFloor => {
+                car.floor > top_floor
+                    || (car.floor == top_floor && moving && car.direction == Direction::Up)
+            }
+        }
+    }
+}
+
+pub struct Violation {
+    pub tick: u64,
+    pub at: Duration,
+    pub elevator: usize,
+    pub rule: SafetyRule,
+    pub state: CarSnapshot,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} violated by elevator {} at tick {} ({:.2}s): {}; state {:?}",
+            self.rule.code(),
+            self.elevator,
+            self.tick,
+            self.at.as_secs_f64(),
+            self.rule.description(),
+            self.state
+        )
+    }
+}
+
+pub struct SafetyMonitor {
+    lowest_floor: i32,
+    top_floor: i32,
+    stop_on_violation: bool,
+    active: Vec<[bool; 3]>,
+    violations: Vec<Violation>,
+}
+
+impl SafetyMonitor {
+    pub fn new(config: &BuildingConfig, stop_on_violation: bool) -> Self {
+        SafetyMonitor {
+            lowest_floor: config.lowest_floor,
+            top_floor: config.top_floor(),
+            stop_on_violation,
+            active: vec![[false; 3]; config.cars],
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.active.iter_mut().for_each(|rules| *rules = [false; 3]);
+        self.violations.clear();
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    pub fn check(&mut self, state: &CarSnapshot, at: Duration) -> bool {
+        let elevator = state.number;
+        let mut stop = false;
+        for (i, rule) in SafetyRule::ALL.into_iter().enumerate() {
+            let violated = rule.is_violated(state, self.lowest_floor, self.top_floor);
+            let was_active = std::mem::replace(&mut self.active[elevator][i], violated);
+            if !violated || was_active {
+                continue;
+            }
+
+            let violation = Violation {
+                tick: at.as_millis() as u64 / TICK.as_millis() as u64,
+                at,
+                elevator,
+                rule,
+                state: *state,
+            };
+            debug(format!("SAFETY {violation}"));
+            self.violations.push(violation);
+            stop |= self.stop_on_violation;
+        }
+        stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::door::DoorState;
+    use crate::elevator::CarMode;
+
+    const LOWEST: i32 = 0;
+    const TOP: i32 = 5;
+
+    fn car(floor: i32, floor_progress: f32, direction: Direction, state: State) -> CarSnapshot {
+        CarSnapshot {
+            number: 0,
+            floor,
+            destination: floor,
+            floor_progress,
+            direction,
+            state,
+            pending_stops: 0,
+            passenger_count: 0,
+            capacity: 8,
+            halted: false,
+            rescue: None,
+            powered: true,
+            steps: 0,
+            faulted: false,
+            lantern: None,
+            mode: CarMode::InService,
+            door: DoorState::Closed,
+            door_progress: 0.0,
+            door_dwell: 0,
+            door_nudging: false,
+            door_reopens: 0,
+        }
+    }
+
+    fn violated(car: &CarSnapshot) -> Vec<SafetyRule> {
+        SafetyRule::ALL
+            .into_iter()
+            .filter(|rule| rule.is_violated(car, LOWEST, TOP))
+            .collect()
+    }
+
+    #[test]
+    fn doors_open_while_driving_is_flagged() {
+        let mut driving = car(2, 0.3, Direction::Up, State::Driving);
+        driving.door = DoorState::Opening;
+        driving.door_progress = 0.2;
+        assert_eq!(violated(&driving), [SafetyRule::DoorsOpenWhileDriving]);
+    }
+
+    #[test]
+    fn moving_down_at_lowest_floor_is_flagged() {
+        let car = car(LOWEST, 0.5, Direction::Down, State::Driving);
+        assert_eq!(violated(&car), [SafetyRule::DownwardAtLowestFloor]);
+    }
+
+    #[test]
+    fn moving_up_at_top_floor_is_flagged() {
+        let car = car(TOP, 0.5, Direction::Up, State::Driving);
+        assert_eq!(violated(&car), [SafetyRule::UpwardAtTopFloor]);
+    }
+
+    #[test]
+    fn open_doors_at_a_floor_are_not_flagged() {
+        for floor in [LOWEST, 2, TOP] {
+            let mut stopped = car(floor, 0.0, Direction::Up, State::Stopped);
+            stopped.door = DoorState::Open;
+            stopped.door_progress = 1.0;
+            assert!(violated(&stopped).is_empty(), "floor {floor} flagged");
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::building::BuildingConfig;
+use crate::elevator::{CarMode, Fault};
+use crate::traffic::{MAX_INTENSITY, MIN_INTENSITY, TrafficGenerator};
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub building: BuildingConfig,
+    pub initial_random_persons: usize,
+    pub initial_persons: Vec<PersonSpec>,
+    pub spawn: Vec<SpawnRule>,
+    pub traffic: TrafficGenerator,
+    pub door_obstruction: f64,
+    pub events: Vec<ScheduledEvent>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PersonSpec {
+    pub floor: i32,
+    pub destination: i32,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnRule {
+    pub floors: Option<Vec<i32>>,
+    pub destinations: Option<Vec<i32>>,
+    pub probability: f64,
+    #[serde(default)]
+    pub from_tick: u64,
+    pub until_tick: Option<u64>,
+    #[serde(default = "default_max_persons")]
+    pub max_persons: usize,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ScheduledEvent {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub action: ScenarioAction,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ScenarioAction {
+    Spawn {
+        count: usize,
+        floor: i32,
+        destination: i32,
+    },
+    ObstructDoor {
+        elevator: usize,
+        #[serde(default = "default_obstruction_ticks")]
+        ticks: u32,
+    },
+    SetMode {
+        elevator: usize,
+        mode: CarMode,
+    },
+    EmergencyStop {
+        elevator: Option<usize>,
+    },
+    Release {
+        elevator: Option<usize>,
+    },
+    FireAlarm,
+    FireReset,
+    PowerFailure,
+    PowerRestored,
+    Evacuate,
+    Firefighter {
+        elevator: usize,
+    },
+    FirefighterCall {
+        floor: i32,
+    },
+    InjectFault {
+        elevator: usize,
+        fault: Fault,
+    },
+    ClearFault {
+        elevator: usize,
+        fault: Fault,
+    },
+}
+
+fn default_obstruction_ticks() -> u32 {
+    1
+}
+
+fn default_max_persons() -> usize {
+    30
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            building: BuildingConfig::default(),
+            initial_random_persons: 5,
+            initial_persons: Vec::new(),
+            spawn: vec![SpawnRule {
+                floors: None,
+                destinations: None,
+                probability: 0.15,
+                from_tick: 0,
+                until_tick: None,
+                max_persons: default_max_persons(),
+            }],
+            traffic: TrafficGenerator::default(),
+            door_obstruction: 0.0,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl SpawnRule {
+    pub fn is_active(&self, tick: u64) -> bool {
+        tick >= self.from_tick && self.until_tick.is_none_or(|until| tick < until)
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read scenario '{path}': {err}"))?;
+        let mut scenario: Scenario = match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|err| err.to_string()),
+            _ => toml::from_str(&text).map_err(|err| err.to_string()),
+        }
+        .map_err(|err| format!("invalid scenario '{path}': {err}"))?;
+
+        scenario.events.sort_by_key(|event| event.tick);
+        Ok(scenario)
+    }
+
+    pub fn validate(&self, config: &BuildingConfig) -> Result<(), String> {
+        let check_floor = |floor: i32, what: &str| {
+            if config.contains(floor) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "scenario {what} {floor} is outside floors {}..={}",
+                    config.lowest_floor,
+                    config.top_floor()
+                ))
+            }
+        };
+
+        for person in &self.initial_persons {
+            check_floor(person.floor, "initial person floor")?;
+            check_floor(person.destination, "initial person destination")?;
+        }
+        for rule in &self.spawn {
+            if !(0.0..=1.0).contains(&rule.probability) {
+                return Err(format!(
+                    "spawn probability must be between 0 and 1, got {}",
+                    rule.probability
+                ));
+            }
+            for &floor in rule.floors.iter().flatten() {
+                check_floor(floor, "spawn floor")?;
+            }
+            for &floor in rule.destinations.iter().flatten() {
+                check_floor(floor, "spawn destination")?;
+            }
+        }
+        if !(0.0..=1.0).contains(&self.door_obstruction) {
+            return Err(format!(
+                "door obstruction probability must be between 0 and 1, got {}",
+                self.door_obstruction
+            ));
+        }
+        if !(MIN_INTENSITY..=MAX_INTENSITY).contains(&self.traffic.intensity) {
+            return Err(format!(
+                "traffic intensity must be between {MIN_INTENSITY} and {MAX_INTENSITY} persons per minute, got {}",
+                self.traffic.intensity
+            ));
+        }
+        for event in &self.events {
+            match &event.action {
+                ScenarioAction::Spawn {
+                    floor, destination, ..
+                } => {
+                    check_floor(*floor, "event floor")?;
+                    check_floor(*destination, "event destination")?;
+                }
+                ScenarioAction::FirefighterCall { floor } => check_floor(*floor, "firefighter call")?,
+                ScenarioAction::ObstructDoor { elevator, .. }
+                | ScenarioAction::SetMode { elevator, .. }
+                | ScenarioAction::InjectFault { elevator, .. }
+                | ScenarioAction::ClearFault { elevator, .. }
+                | ScenarioAction::Firefighter { elevator }
+                | ScenarioAction::EmergencyStop {
+                    elevator: Some(elevator),
+                }
+                | ScenarioAction::Release {
+                    elevator: Some(elevator),
+                } => {
+                    if *elevator >= config.cars {
+                        return Err(format!(
+                            "scenario event elevator {elevator} does not exist, building has {} cars",
+                            config.cars
+                        ));
+                    }
+                }
+                ScenarioAction::EmergencyStop { elevator: None }
+                | ScenarioAction::Release { elevator: None }
+                | ScenarioAction::FireAlarm
+                | ScenarioAction::FireReset
+                | ScenarioAction::PowerFailure
+                | ScenarioAction::PowerRestored
+                | ScenarioAction::Evacuate => {}
+            }
+        }
+        Ok(())
+    }
+}
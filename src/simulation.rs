@@ -0,0 +1,470 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::building::BuildingConfig;
+use crate::clock::{CAR_STEP, SimClock, TICK};
+use crate::debug;
+use crate::dispatch::DispatchStrategy;
+use crate::door::DoorState;
+use crate::elevator::{CarMode, Stepping};
+use crate::elevator_controller::{ElevatorController, FireService};
+use crate::evacuation::Evacuation;
+use crate::events::{CarEvent, CarEventKind};
+use crate::metrics::{Journey, MetricsStore};
+use crate::person::{PassengerAction, PassengerState, Person};
+use crate::safety::SafetyMonitor;
+use crate::scenario::{Scenario, ScenarioAction, SpawnRule};
+use crate::stats::RunStats;
+use crate::traffic::{TrafficGenerator, TrafficPattern};
+
+const MAX_TICKS_PER_ADVANCE: u32 = 1000;
+const RANDOM_OBSTRUCTION_STEPS: u32 = 3;
+const DOORWAY_OBSTRUCTION_STEPS: u32 = 3;
+
+pub struct Simulation {
+    pub controller: ElevatorController,
+    pub persons: Vec<Person>,
+    pub clock: SimClock,
+    pub ticks: u64,
+    pub manual: bool,
+    pub stats: RunStats,
+    pub metrics: MetricsStore,
+    pub traffic: TrafficGenerator,
+    pub safety: Arc<Mutex<SafetyMonitor>>,
+    pub evacuation: Option<Evacuation>,
+    next_person_id: u64,
+    car_events: Receiver<CarEvent>,
+    landings: Vec<Option<i32>>,
+    scenario: Scenario,
+    next_event: usize,
+    stepping: Stepping,
+    seed: Option<u64>,
+    rng: StdRng,
+    next_tick: Duration,
+    lockstep_budget: Duration,
+}
+
+impl Simulation {
+    pub fn new(
+        config: &BuildingConfig,
+        strategy: Box<dyn DispatchStrategy>,
+        seed: Option<u64>,
+        scenario: Scenario,
+        safety_stop: bool,
+    ) -> Self {
+        let stepping = match seed {
+            Some(_) => Stepping::Lockstep,
+            None => Stepping::Threaded,
+        };
+        let clock = SimClock::new();
+        let safety = Arc::new(Mutex::new(SafetyMonitor::new(config, safety_stop)));
+        let controller = ElevatorController::new(config, strategy, stepping, &clock, &safety);
+        let car_events = controller.subscribe();
+        let landings = open_landings(&controller);
+
+        let mut simulation = Simulation {
+            controller,
+            persons: Vec::new(),
+            next_tick: clock.now(),
+            clock,
+            ticks: 0,
+            manual: false,
+            stats: RunStats::new(config),
+            metrics: MetricsStore::default(),
+            next_person_id: 0,
+            car_events,
+            landings,
+            traffic: scenario.traffic,
+            safety,
+            evacuation: None,
+            scenario,
+            next_event: 0,
+            stepping,
+            seed,
+            rng: new_rng(seed),
+            lockstep_budget: Duration::ZERO,
+        };
+        if let Some(seed) = seed {
+            debug(format!("Deterministic run with seed {seed}"));
+        }
+        simulation.spawn_initial_persons();
+        simulation
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_person_id += 1;
+        self.next_person_id
+    }
+
+    fn spawn_random_person(&mut self) {
+        if self.evacuation.is_some() {
+            return;
+        }
+        let id = self.next_id();
+        let person = Person::new_rnd(id, self.controller.config(), &mut self.rng, self.clock.now());
+        self.persons.push(person);
+    }
+
+    fn spawn_initial_persons(&mut self) {
+        for _ in 0..self.scenario.initial_random_persons {
+            self.spawn_random_person();
+        }
+        for i in 0..self.scenario.initial_persons.len() {
+            let spec = &self.scenario.initial_persons[i];
+            self.spawn_person(spec.floor, spec.destination);
+        }
+    }
+
+    fn apply_spawn_rules(&mut self) {
+        for i in 0..self.scenario.spawn.len() {
+            let rule = &self.scenario.spawn[i];
+            if !rule.is_active(self.ticks) || self.persons.len() >= rule.max_persons {
+                continue;
+            }
+            if !self.rng.random_bool(rule.probability) {
+                continue;
+            }
+            if rule.floors.is_none() && rule.destinations.is_none() {
+                self.spawn_random_person();
+            } else if let Some((floor, destination)) =
+                pick_trip(rule, self.controller.config(), &mut self.rng)
+            {
+                self.spawn_person(floor, destination);
+            }
+        }
+    }
+
+    fn apply_traffic(&mut self) {
+        let arrivals = self.traffic.arrivals(&mut self.rng, TICK);
+        for _ in 0..arrivals {
+            let (floor, destination) = self.traffic.trip(self.controller.config(), &mut self.rng);
+            self.spawn_person(floor, destination);
+        }
+    }
+
+    fn apply_door_sensor(&mut self) {
+        let probability = self.scenario.door_obstruction;
+        if probability == 0.0 {
+            return;
+        }
+        for car in self.controller.snapshot().cars {
+            if car.mode == CarMode::InService && car.door == DoorState::Closing && self.rng.random_bool(probability) {
+                debug(format!("Door sensor of elevator {} detected an obstruction", car.number));
+                self.controller
+                    .get_elevator(car.number as i32)
+                    .obstruct_door(RANDOM_OBSTRUCTION_STEPS);
+            }
+        }
+    }
+
+    fn apply_scheduled_events(&mut self) {
+        while let Some(event) = self.scenario.events.get(self.next_event) {
+            if event.tick > self.ticks {
+                break;
+            }
+            self.next_event += 1;
+            match event.action {
+                ScenarioAction::Spawn {
+                    count,
+                    floor,
+                    destination,
+                } => {
+                    debug(format!(
+                        "Scenario: spawning {count} persons from floor {floor} to {destination}"
+                    ));
+                    for _ in 0..count {
+                        self.spawn_person(floor, destination);
+                    }
+                }
+                ScenarioAction::ObstructDoor { elevator, ticks } => {
+                    debug(format!("Scenario: obstructing door of elevator {elevator} for {ticks} ticks"));
+                    self.controller
+                        .get_elevator(elevator as i32)
+                        .obstruct_door(ticks_to_steps(ticks));
+                }
+                ScenarioAction::SetMode { elevator, mode } => {
+                    debug(format!("Scenario: elevator {elevator} to {}", mode.name()));
+                    self.controller.set_mode(elevator, mode);
+                }
+                ScenarioAction::EmergencyStop { elevator } => {
+                    debug("Scenario: emergency stop");
+                    self.controller.emergency_stop(elevator);
+                }
+                ScenarioAction::Release { elevator } => {
+                    debug("Scenario: release emergency stop");
+                    self.controller.release(elevator);
+                }
+                ScenarioAction::Evacuate => self.start_evacuation(),
+                ScenarioAction::PowerFailure => self.controller.power_failure(),
+                ScenarioAction::PowerRestored => self.controller.restore_power(),
+                ScenarioAction::FireAlarm => self.controller.set_fire_service(FireService::PhaseOne),
+                ScenarioAction::FireReset => self.controller.set_fire_service(FireService::Off),
+                ScenarioAction::Firefighter { elevator } => {
+                    self.controller.set_fire_service(FireService::PhaseTwo(elevator))
+                }
+                ScenarioAction::FirefighterCall { floor } => self.controller.firefighter_call(floor),
+                ScenarioAction::InjectFault { elevator, fault } => {
+                    self.controller
+                        .get_elevator(elevator as i32)
+                        .set_fault(fault, true);
+                }
+                ScenarioAction::ClearFault { elevator, fault } => {
+                    self.controller
+                        .get_elevator(elevator as i32)
+                        .set_fault(fault, false);
+                }
+            }
+        }
+    }
+
+    fn track_landings(&mut self) {
+        for event in self.car_events.try_iter() {
+            match event.kind {
+                CarEventKind::DoorsOpened => self.landings[event.elevator] = Some(event.floor),
+                CarEventKind::DoorsClosed => self.landings[event.elevator] = None,
+                CarEventKind::Reset => {
+                    self.landings[event.elevator] = open_landings(&self.controller)[event.elevator]
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn spawn_person(&mut self, floor: i32, destination: i32) {
+        if self.evacuation.is_some() {
+            debug(format!("Evacuation in progress, not spawning person on floor {floor}"));
+            return;
+        }
+        let id = self.next_id();
+        self.persons
+            .push(Person::new(id, floor, destination, self.clock.now()));
+    }
+
+    pub fn start_evacuation(&mut self) {
+        if self.evacuation.is_some() {
+            return;
+        }
+        self.controller.start_evacuation();
+        let exit_floor = self.controller.config().lobby_floor();
+        self.persons
+            .retain(|person| person.in_elevator() || person.floor != exit_floor);
+        for person in &mut self.persons {
+            person.destination = exit_floor;
+        }
+        self.evacuation = Some(Evacuation::new(exit_floor, &self.persons, self.clock.now()));
+    }
+
+    pub fn reset(&mut self) {
+        self.controller.reset();
+        self.car_events.try_iter().for_each(drop);
+        self.landings = open_landings(&self.controller);
+        self.persons.clear();
+        self.stats = RunStats::new(self.controller.config());
+        self.metrics = MetricsStore::default();
+        self.safety.lock().unwrap().reset();
+        self.evacuation = None;
+        self.next_person_id = 0;
+        self.ticks = 0;
+        self.next_event = 0;
+        self.rng = new_rng(self.seed);
+        self.spawn_initial_persons();
+    }
+
+    pub fn advance(&mut self, real: Duration) {
+        let sim = self.clock.scaled(real);
+        let mut ticks = 0;
+
+        match self.stepping {
+            Stepping::Threaded => {
+                self.clock.advance(sim);
+                while self.next_tick <= self.clock.now() && ticks < MAX_TICKS_PER_ADVANCE {
+                    self.update();
+                    self.next_tick += TICK;
+                    ticks += 1;
+                }
+                if self.next_tick <= self.clock.now() {
+                    self.next_tick = self.clock.now() + TICK;
+                }
+            }
+            Stepping::Lockstep => {
+                self.lockstep_budget += sim;
+                while self.lockstep_budget >= TICK && ticks < MAX_TICKS_PER_ADVANCE {
+                    self.lockstep_budget -= TICK;
+                    self.tick();
+                    ticks += 1;
+                }
+                self.lockstep_budget = self.lockstep_budget.min(TICK);
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.clock.advance(TICK);
+        self.controller.step_elevators_until(self.clock.now());
+        self.update();
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        self.ticks += 1;
+
+        self.controller.update();
+
+        if !self.manual && self.evacuation.is_none() {
+            if self.traffic.pattern == TrafficPattern::Off {
+                self.apply_spawn_rules();
+            } else {
+                self.apply_traffic();
+            }
+        }
+        self.apply_door_sensor();
+        self.apply_scheduled_events();
+        self.track_landings();
+
+        let controller = &mut self.controller;
+        let persons = &mut self.persons;
+        let cars = controller.snapshot().cars;
+        let mut lanterns: Vec<_> = cars.iter().map(|car| car.lantern).collect();
+        for person in persons.iter() {
+            if let PassengerState::Entering { elevator, .. }
+            | PassengerState::ChoosingDestination { elevator, .. } = person.state
+            {
+                lanterns[elevator].get_or_insert(person.direction());
+            }
+        }
+
+        let mut to_remove = Vec::new();
+
+        for (i, person) in persons.iter_mut().enumerate() {
+            if let Some(elevator) = person.elevator()
+                && cars[elevator].halted
+                && self.landings[elevator].is_none()
+            {
+                person.trapped += TICK;
+            }
+            match person.state {
+                PassengerState::Idle => {
+                    let mut entered = false;
+                    for (elevator, landing) in self.landings.iter().enumerate() {
+                        if *landing == Some(person.floor)
+                            && controller.mode(elevator) == CarMode::InService
+                            && lanterns[elevator].is_none_or(|lantern| lantern == person.direction())
+                            && person.enter_elevator(elevator, controller)
+                        {
+                            lanterns[elevator] = Some(person.direction());
+                            debug(format!("Passenger entering elevator {elevator} on floor {}", person.floor));
+                            entered = true;
+                            break;
+                        }
+                    }
+
+                    if !entered {
+                        person.press_button_up_or_down(controller);
+                    }
+                }
+                PassengerState::Riding { elevator } => {
+                    if let Some(landing) = self.landings[elevator]
+                        && controller.mode(elevator) != CarMode::OutOfService
+                        && (landing == person.destination
+                            || cars[elevator].halted
+                            || controller.mode(elevator) == CarMode::FireRecall)
+                    {
+                        person.start_leaving(elevator);
+                    }
+                }
+                PassengerState::Entering { elevator, .. }
+                | PassengerState::ChoosingDestination { elevator, .. }
+                | PassengerState::Leaving { elevator, .. } => {
+                    match person.advance(cars[elevator].door, now) {
+                        PassengerAction::Wait => {}
+                        PassengerAction::HoldDoor => controller
+                            .get_elevator(elevator as i32)
+                            .obstruct_door(DOORWAY_OBSTRUCTION_STEPS),
+                        PassengerAction::Boarded => {
+                            debug(format!("Added passenger from floor {}", person.floor));
+                        }
+                        PassengerAction::PressButton => person.press_floor_button(controller),
+                        PassengerAction::StepBack => {
+                            debug(format!(
+                                "Passenger stepped back from elevator {elevator} on floor {}",
+                                person.floor
+                            ));
+                            person.leave_elevator(elevator, controller);
+                        }
+                        PassengerAction::Alighted => {
+                            let landing = self.landings[elevator].unwrap_or(cars[elevator].floor);
+                            person.leave_elevator(elevator, controller);
+                            if landing != person.destination {
+                                debug(format!(
+                                    "Passenger left elevator {elevator} early at floor {landing}"
+                                ));
+                                person.floor = landing;
+                                continue;
+                            }
+                            if let Some(journey) = Journey::complete(person, elevator as i32, now) {
+                                self.metrics.record(journey);
+                            }
+
+                            to_remove.push(i);
+                        }
+                    }
+                }
+            }
+        }
+
+        for i in to_remove.into_iter().rev() {
+            persons.remove(i);
+        }
+
+        if let Some(evacuation) = &mut self.evacuation
+            && evacuation.completed_at.is_none()
+        {
+            evacuation.update(persons, now);
+            if let Some(total) = evacuation.completed_at {
+                debug(format!("Evacuation complete after {:.1}s", total.as_secs_f64()));
+            }
+        }
+
+        self.stats.sample(&controller.snapshot().cars, persons);
+    }
+}
+
+fn open_landings(controller: &ElevatorController) -> Vec<Option<i32>> {
+    controller
+        .snapshot()
+        .cars
+        .iter()
+        .map(|car| (car.door != DoorState::Closed).then_some(car.floor))
+        .collect()
+}
+
+fn pick_trip(rule: &SpawnRule, config: &BuildingConfig, rng: &mut StdRng) -> Option<(i32, i32)> {
+    let all_floors: Vec<i32> = config.floor_range().collect();
+    let origins = rule.floors.as_deref().unwrap_or(&all_floors);
+    let floor = *origins.get(rng.random_range(0..origins.len().max(1)))?;
+
+    let destinations: Vec<i32> = rule
+        .destinations
+        .as_deref()
+        .unwrap_or(&all_floors)
+        .iter()
+        .copied()
+        .filter(|&d| d != floor)
+        .collect();
+    let destination = *destinations.get(rng.random_range(0..destinations.len().max(1)))?;
+    Some((floor, destination))
+}
+
+fn ticks_to_steps(ticks: u32) -> u32 {
+    (TICK.as_micros() * ticks as u128 / CAR_STEP.as_micros()) as u32
+}
+
+fn new_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
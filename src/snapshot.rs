@@ -0,0 +1,74 @@
+
+use crate::door::{Door, DoorState};
+use crate::elevator::{CarMode, Direction, ElevatorState, Rescue, State};
+
+#[derive(Clone, Copy, Debug)]
+pub struct CarSnapshot {
+    pub number: usize,
+    pub floor: i32,
+    pub destination: i32,
+    pub floor_progress: f32,
+    pub direction: Direction,
+    pub state: State,
+    pub pending_stops: usize,
+    pub passenger_count: i32,
+    pub capacity: i32,
+    pub halted: bool,
+    pub rescue: Option<Rescue>,
+    pub powered: bool,
+    pub steps: u64,
+    pub faulted: bool,
+    pub lantern: Option<Direction>,
+    pub mode: CarMode,
+    pub door: DoorState,
+    pub door_progress: f32,
+    pub door_dwell: u32,
+    pub door_nudging: bool,
+    pub door_reopens: u32,
+}
+
+impl CarSnapshot {
+    pub fn new(cab: &ElevatorState, door: &Door) -> Self {
+        CarSnapshot {
+            number: cab.number,
+            // The controller only sees what the position sensor reports.
+            floor: cab.floor + cab.sensor_error,
+            destination: cab.destination,
+            floor_progress: cab.floor_progress,
+            direction: cab.direction,
+            state: cab.state,
+            pending_stops: cab.requests.len(),
+            passenger_count: cab.passenger_count,
+            capacity: cab.capacity,
+            halted: cab.halted,
+            rescue: cab.rescue,
+            powered: cab.powered,
+            steps: cab.steps,
+            faulted: !cab.faults.is_empty(),
+            lantern: cab.lantern,
+            mode: CarMode::InService,
+            door: door.state,
+            door_progress: door.progress,
+            door_dwell: door.dwell,
+            door_nudging: door.nudging,
+            door_reopens: door.total_reopens,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.passenger_count >= self.capacity
+    }
+
+    pub fn is_at_floor(&self, floor: i32) -> bool {
+        self.floor == floor && self.state != State::Driving && self.door != DoorState::Closed
+    }
+
+    pub fn serves(&self, floor: i32, direction: Direction) -> bool {
+        self.is_at_floor(floor) && self.lantern.is_none_or(|lantern| lantern == direction)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub cars: Vec<CarSnapshot>,
+}
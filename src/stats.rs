@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::building::BuildingConfig;
+use crate::elevator::State;
+use crate::snapshot::CarSnapshot;
+use crate::person::Person;
+
+pub struct RunStats {
+    lowest_floor: i32,
+    pub ticks: u64,
+    pub max_queue: Vec<usize>,
+    pub busy_ticks: Vec<u64>,
+    pub door_reopens: Vec<u32>,
+}
+
+impl RunStats {
+    pub fn new(config: &BuildingConfig) -> Self {
+        RunStats {
+            lowest_floor: config.lowest_floor,
+            ticks: 0,
+            max_queue: vec![0; config.floors as usize],
+            busy_ticks: vec![0; config.cars],
+            door_reopens: vec![0; config.cars],
+        }
+    }
+
+    pub fn sample(&mut self, cars: &[CarSnapshot], persons: &[Person]) {
+        self.ticks += 1;
+
+        let mut queue = vec![0; self.max_queue.len()];
+        for person in persons.iter().filter(|p| !p.in_elevator()) {
+            if let Some(count) = queue.get_mut((person.floor - self.lowest_floor) as usize) {
+                *count += 1;
+            }
+        }
+        for (max, count) in self.max_queue.iter_mut().zip(queue) {
+            *max = (*max).max(count);
+        }
+
+        for (busy, car) in self.busy_ticks.iter_mut().zip(cars) {
+            let idle = car.state == State::Idle
+                && car.pending_stops == 0
+                && car.passenger_count == 0;
+            if !idle {
+                *busy += 1;
+            }
+        }
+        for (reopens, car) in self.door_reopens.iter_mut().zip(cars) {
+            *reopens = car.door_reopens;
+        }
+    }
+}
+
+impl fmt::Display for RunStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Max queue per floor:")?;
+        for (i, max) in self.max_queue.iter().enumerate().rev() {
+            writeln!(f, "  floor {:>3}: {}", self.lowest_floor + i as i32, max)?;
+        }
+        writeln!(f, "Car utilisation:")?;
+        for (car, busy) in self.busy_ticks.iter().enumerate() {
+            let utilisation = if self.ticks == 0 {
+                0.0
+            } else {
+                *busy as f64 / self.ticks as f64 * 100.0
+            };
+            writeln!(f, "  car {car}: {utilisation:.1}%")?;
+        }
+        writeln!(f, "Door reopens:")?;
+        for (car, reopens) in self.door_reopens.iter().enumerate() {
+            writeln!(f, "  car {car}: {reopens}")?;
+        }
+        Ok(())
+    }
+}
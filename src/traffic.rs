@@ -0,0 +1,142 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::building::BuildingConfig;
+
+const LOBBY_SHARE: f64 = 0.9;
+const LUNCH_LOBBY_SHARE: f64 = 0.4;
+
+pub const MIN_INTENSITY: f64 = 0.5;
+pub const MAX_INTENSITY: f64 = 600.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrafficPattern {
+    Off,
+    InterFloor,
+    UpPeak,
+    DownPeak,
+    Lunch,
+}
+
+impl TrafficPattern {
+    pub const ALL: [TrafficPattern; 5] = [
+        TrafficPattern::Off,
+        TrafficPattern::InterFloor,
+        TrafficPattern::UpPeak,
+        TrafficPattern::DownPeak,
+        TrafficPattern::Lunch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TrafficPattern::Off => "off",
+            TrafficPattern::InterFloor => "inter-floor",
+            TrafficPattern::UpPeak => "up-peak",
+            TrafficPattern::DownPeak => "down-peak",
+            TrafficPattern::Lunch => "lunch",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&p| p == self).unwrap();
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for TrafficPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|p| p.name()).collect();
+                format!("unknown traffic pattern '{s}', expected one of {}", names.join(", "))
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrafficGenerator {
+    pub pattern: TrafficPattern,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+fn default_intensity() -> f64 {
+    30.0
+}
+
+impl Default for TrafficGenerator {
+    fn default() -> Self {
+        TrafficGenerator {
+            pattern: TrafficPattern::Off,
+            intensity: default_intensity(),
+        }
+    }
+}
+
+impl TrafficGenerator {
+    pub fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity.clamp(MIN_INTENSITY, MAX_INTENSITY);
+    }
+
+    pub fn arrivals(&self, rng: &mut impl Rng, dt: Duration) -> u32 {
+        if self.pattern == TrafficPattern::Off {
+            return 0;
+        }
+        poisson(rng, self.intensity / 60.0 * dt.as_secs_f64())
+    }
+
+    pub fn trip(&self, config: &BuildingConfig, rng: &mut impl Rng) -> (i32, i32) {
+        let lobby = config.lobby_floor();
+        match self.pattern {
+            TrafficPattern::UpPeak if rng.random_bool(LOBBY_SHARE) => {
+                (lobby, random_floor_except(config, rng, lobby))
+            }
+            TrafficPattern::DownPeak if rng.random_bool(LOBBY_SHARE) => {
+                (random_floor_except(config, rng, lobby), lobby)
+            }
+            TrafficPattern::Lunch => {
+                let roll: f64 = rng.random();
+                if roll < LUNCH_LOBBY_SHARE {
+                    (random_floor_except(config, rng, lobby), lobby)
+                } else if roll < 2.0 * LUNCH_LOBBY_SHARE {
+                    (lobby, random_floor_except(config, rng, lobby))
+                } else {
+                    random_inter_floor_trip(config, rng)
+                }
+            }
+            _ => random_inter_floor_trip(config, rng),
+        }
+    }
+}
+
+fn random_floor_except(config: &BuildingConfig, rng: &mut impl Rng, except: i32) -> i32 {
+    loop {
+        let floor = rng.random_range(config.floor_range());
+        if floor != except {
+            return floor;
+        }
+    }
+}
+
+fn random_inter_floor_trip(config: &BuildingConfig, rng: &mut impl Rng) -> (i32, i32) {
+    let floor = rng.random_range(config.floor_range());
+    (floor, random_floor_except(config, rng, floor))
+}
+
+fn poisson(rng: &mut impl Rng, lambda: f64) -> u32 {
+    let limit = (-lambda).exp();
+    let mut k = 0;
+    let mut p: f64 = rng.random();
+    while p > limit {
+        k += 1;
+        p *= rng.random::<f64>();
+    }
+    k
+}
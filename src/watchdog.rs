@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Default)]
+pub struct Heartbeat(Arc<AtomicU64>);
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct Watchdog {
+    last_seen: Vec<Vec<(u64, Instant)>>,
+}
+
+impl Watchdog {
+    pub fn new(cars: usize) -> Self {
+        Watchdog {
+            last_seen: vec![Vec::new(); cars],
+        }
+    }
+
+    pub fn forget(&mut self, car: usize) {
+        self.last_seen[car].clear();
+    }
+
+    pub fn is_responsive(&mut self, car: usize, beats: &[u64]) -> bool {
+        let now = Instant::now();
+        let seen = &mut self.last_seen[car];
+        seen.resize(beats.len(), (u64::MAX, now));
+
+        let mut responsive = true;
+        for ((count, at), &beat) in seen.iter_mut().zip(beats) {
+            if *count != beat {
+                *count = beat;
+                *at = now;
+            } else if now - *at > WATCHDOG_TIMEOUT {
+                responsive = false;
+            }
+        }
+        responsive
+    }
+}